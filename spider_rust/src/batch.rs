@@ -0,0 +1,416 @@
+use crate::deck::Deck;
+use crate::evaluator::{DefaultEvaluator, Evaluator, WeightedEvaluator, Weights};
+use crate::pile::Pile;
+use crate::progress::Observer;
+use crate::solution::{Format, Solution};
+use crate::solver::{Budget, Strategy};
+use crate::tune::{collect_games, suits_of, TuneGame};
+use std::collections::HashMap;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+// how every game of the batch is solved. The piles are only shared
+// within a thread, so each worker builds its own evaluator
+#[derive(Clone, Copy)]
+pub struct BatchSettings {
+    pub strategy: Strategy,
+    pub budget: Budget,
+    // given or taken from the clock once, and written to every solution
+    pub seed: u64,
+    pub weights: Option<Weights>,
+    pub format: Format,
+}
+
+pub struct BatchResult {
+    pub game: String,
+    pub length: Option<usize>,
+    pub nodes: usize,
+    pub seconds: f64,
+    // why the game couldn't be solved, the others go on
+    pub error: Option<String>,
+}
+
+// game files as they are, directories like tune collects them. The
+// suits come from a directory named like 1suits
+pub fn collect(paths: &[&str], suits: usize) -> Vec<TuneGame> {
+    let mut games = vec![];
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            games.extend(collect_games(path, suits));
+        } else {
+            games.push(TuneGame {
+                filename: path.to_string_lossy().to_string(),
+                suits: path.parent().map_or(suits, |dir| suits_of(dir, suits)),
+            });
+        }
+    }
+    games
+}
+
+pub fn solve(
+    game: &TuneGame,
+    settings: &BatchSettings,
+    evaluator: &dyn Evaluator,
+) -> Result<Solution, String> {
+    // the same result no matter what the worker solved before. Decks
+    // of earlier games must not meet the ones of this game from now on
+    Pile::reset_tree();
+    let contents = fs::read_to_string(&game.filename)
+        .map_err(|why| format!("couldn't read {}: {}", game.filename, why))?;
+    let mut deck = Deck::try_parse(&contents)?;
+    deck.shuffle_unknowns_with_seed(game.suits, settings.seed);
+    let mut solution = Solution::new(&game.filename, &deck);
+    let start = Instant::now();
+    let mut solver = settings.strategy.solver(evaluator, false, settings.seed);
+    let mut observer = Observer::default();
+    let outcome = solver.solve(&deck, &settings.budget, &mut observer);
+    solution.suits = game.suits;
+    solution.seed = Some(settings.seed);
+    solution.cap = settings.budget.cap;
+    solution.nodes = observer.stats.expanded;
+    solution.seconds = start.elapsed().as_secs_f64();
+    if let Some(moves) = outcome.moves {
+        solution.length = Some(moves.len());
+        solution.set_moves(&moves);
+    }
+    Ok(solution)
+}
+
+// a panicking game is reported like one that couldn't be read
+fn solve_caught(
+    game: &TuneGame,
+    settings: &BatchSettings,
+    evaluator: &dyn Evaluator,
+) -> Result<Solution, String> {
+    panic::catch_unwind(AssertUnwindSafe(|| solve(game, settings, evaluator))).unwrap_or_else(
+        |payload| {
+            let why = match payload.downcast_ref::<&str>() {
+                Some(why) => why.to_string(),
+                None => payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .unwrap_or_else(|| "unknown panic".to_string()),
+            };
+            Err(format!("panicked: {}", why))
+        },
+    )
+}
+
+// the file names of the games, the whole path where two games share
+// a name
+pub fn solution_names(games: &[TuneGame]) -> Vec<String> {
+    let name = |game: &TuneGame| {
+        Path::new(&game.filename)
+            .file_name()
+            .map_or(game.filename.clone(), |name| {
+                name.to_string_lossy().to_string()
+            })
+    };
+    let mut counts = HashMap::new();
+    for game in games {
+        *counts.entry(name(game)).or_insert(0) += 1;
+    }
+    games
+        .iter()
+        .map(|game| match counts[&name(game)] {
+            1 => name(game),
+            _ => game
+                .filename
+                .trim_start_matches("./")
+                .trim_start_matches("../")
+                .replace(['/', '\\'], "_"),
+        })
+        .collect()
+}
+
+// named like the archived results, example7.game.500.yaml
+pub fn solution_path(output: &Path, name: &str, cap: usize, format: Format) -> String {
+    let extension = match format {
+        Format::Yaml => "yaml",
+        Format::Json => "json",
+    };
+    output
+        .join(format!("{}.{}.{}", name, cap, extension))
+        .to_string_lossy()
+        .to_string()
+}
+
+// the games are handed out one by one to the workers, the results
+// come back in the order of the games
+pub fn run(
+    games: &[TuneGame],
+    settings: &BatchSettings,
+    output: &Path,
+    threads: usize,
+) -> Vec<BatchResult> {
+    fs::create_dir_all(output)
+        .unwrap_or_else(|why| panic!("couldn't create {}: {}", output.display(), why));
+    let names = solution_names(games);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![]);
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                let evaluator: Box<dyn Evaluator> = match settings.weights {
+                    Some(weights) => Box::new(WeightedEvaluator::new(weights)),
+                    None => Box::new(DefaultEvaluator),
+                };
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= games.len() {
+                        break;
+                    }
+                    let game = &games[index];
+                    let mut result = BatchResult {
+                        game: game.filename.clone(),
+                        length: None,
+                        nodes: 0,
+                        seconds: 0.,
+                        error: None,
+                    };
+                    match solve_caught(game, settings, evaluator.as_ref()) {
+                        Ok(solution) => {
+                            let path = solution_path(
+                                output,
+                                &names[index],
+                                settings.budget.cap,
+                                settings.format,
+                            );
+                            if let Err(why) = fs::write(&path, solution.to_text(settings.format)) {
+                                result.error = Some(format!("couldn't write {}: {}", path, why));
+                            }
+                            result.length = solution.length;
+                            result.nodes = solution.nodes;
+                            result.seconds = solution.seconds;
+                        }
+                        Err(why) => result.error = Some(why),
+                    }
+                    results.lock().unwrap().push((index, result));
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+pub fn report(results: &[BatchResult]) -> String {
+    let width = results.iter().map(|r| r.game.len()).max().unwrap_or(0);
+    let mut result = format!(
+        "{:<width$} {:>6} {:>6} {:>10} {:>9}\n",
+        "game",
+        "result",
+        "moves",
+        "nodes",
+        "seconds",
+        width = width
+    );
+    for r in results {
+        result += &format!(
+            "{:<width$} {:>6} {:>6} {:>10} {:>9.2}\n",
+            r.game,
+            match (&r.error, r.length) {
+                (Some(_), _) => "error",
+                (None, Some(_)) => "won",
+                (None, None) => "lost",
+            },
+            r.length.map_or("-".to_string(), |moves| moves.to_string()),
+            r.nodes,
+            r.seconds,
+            width = width
+        );
+    }
+    for r in results {
+        if let Some(why) = &r.error {
+            result += &format!("{}: {}\n", r.game, why);
+        }
+    }
+    let won: Vec<usize> = results.iter().filter_map(|r| r.length).collect();
+    let moves: usize = won.iter().sum();
+    result += &format!("Won {} of {} games", won.len(), results.len());
+    let errors = results.iter().filter(|r| r.error.is_some()).count();
+    if errors > 0 {
+        result += &format!(", {} failed", errors);
+    }
+    if !won.is_empty() {
+        result += &format!(
+            ", {} moves, {:.1} on average",
+            moves,
+            moves as f64 / won.len() as f64
+        );
+    }
+    result += &format!(
+        "\nSearched {} positions in {:.2} seconds\n",
+        results.iter().map(|r| r.nodes).sum::<usize>(),
+        results.iter().map(|r| r.seconds).sum::<f64>()
+    );
+    result
+}
+
+// the lengths of the beam search and another strategy side by side,
+// the moves summed up over the games both won
+pub fn compare(name: &str, beam: &[BatchResult], other: &[BatchResult]) -> String {
+    let width = beam.iter().map(|r| r.game.len()).max().unwrap_or(0);
+    let mut result = format!(
+        "{:<width$} {:>6} {:>6} {:>9} {:>9}\n",
+        "game",
+        "beam",
+        name,
+        "beam sec",
+        format!("{} sec", name),
+        width = width
+    );
+    let show = |r: &BatchResult| match (&r.error, r.length) {
+        (Some(_), _) => "error".to_string(),
+        (None, length) => length.map_or("-".to_string(), |moves| moves.to_string()),
+    };
+    for (b, o) in beam.iter().zip(other.iter()) {
+        result += &format!(
+            "{:<width$} {:>6} {:>6} {:>9.2} {:>9.2}\n",
+            b.game,
+            show(b),
+            show(o),
+            b.seconds,
+            o.seconds,
+            width = width
+        );
+    }
+    let won = |results: &[BatchResult]| results.iter().filter(|r| r.length.is_some()).count();
+    let both: Vec<(usize, usize)> = beam
+        .iter()
+        .zip(other.iter())
+        .filter_map(|(b, o)| Some((b.length?, o.length?)))
+        .collect();
+    result += &format!(
+        "Won {} and {} of {} games, {} and {} moves in the {} both won\n",
+        won(beam),
+        won(other),
+        beam.len(),
+        both.iter().map(|(b, _)| b).sum::<usize>(),
+        both.iter().map(|(_, o)| o).sum::<usize>(),
+        both.len()
+    );
+    result
+}
+
+#[cfg(test)]
+mod batchtests {
+    use super::*;
+    use crate::deck::ENDGAME;
+
+    #[test]
+    fn endgames() {
+        let dir = std::env::temp_dir().join(format!("spider-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let won = ENDGAME;
+        // no top card fits on another and nothing is left to deal
+        let lost = "Play0: |KS |QS AS
+Play1: |KH |QH AH
+Play2: |JS |JH 3S
+Play3: |TS |TH 3H
+Play4: |8S |8H 5S
+Play5: |6S |6H 5H
+Play6: |4S |4H 7S
+Play7: |2S 7H
+Play8: |2H 9S
+Play9: 9H
+Deal0:
+Deal1:
+Deal2:
+Deal3:
+Deal4:
+Off: KS KS KS KH KH KH";
+        let mut paths = vec![];
+        let broken = "Play0: XX";
+        // five spades suits in the off of a two suit deal, the
+        // shuffling panics
+        let panics = won.replace("Off: KS KS KS KS KH KH KH", "Off: KS KS KS KS KS");
+        let files = [
+            ("won.game", won),
+            ("lost.game", lost),
+            ("broken.game", broken),
+            ("panics.game", &panics),
+        ];
+        for (name, text) in files.iter() {
+            let path = dir.join(name).to_string_lossy().to_string();
+            fs::write(&path, text).unwrap();
+            paths.push(path);
+        }
+        let paths: Vec<&str> = paths.iter().map(|path| path.as_str()).collect();
+        let games = collect(&paths, 2);
+        let settings = BatchSettings {
+            strategy: Strategy::Beam,
+            budget: Budget {
+                cap: 50,
+                seen_limit: None,
+                seconds: None,
+                iterations: None,
+            },
+            seed: 1,
+            weights: None,
+            format: Format::Yaml,
+        };
+        let output = dir.join("results");
+        let results = run(&games, &settings, &output, 2);
+        assert_eq!(results.len(), 4);
+        assert!(results[0].game.ends_with("won.game"));
+        assert_eq!(results[0].length, Some(3));
+        assert_eq!(results[1].length, None);
+        assert!(results[1].error.is_none());
+        // the broken games don't stop the others
+        assert!(results[2].error.is_some());
+        assert!(results[3].error.as_ref().unwrap().contains("panicked"));
+        let contents =
+            fs::read_to_string(output.join("won.game.50.yaml")).expect("solution written");
+        let solution = Solution::parse(&contents).unwrap();
+        assert_eq!(solution.moves.len(), 3);
+        assert_eq!(solution.seed, Some(1));
+        // the positions the beam expanded, not all it generated
+        assert_eq!(solution.nodes, 4);
+        assert!(output.join("lost.game.50.yaml").is_file());
+        let report = report(&results);
+        assert!(report.contains("Won 1 of 4 games, 2 failed, 3 moves, 3.0 on average"));
+        let nrpa = BatchSettings {
+            strategy: Strategy::Nrpa,
+            budget: Budget {
+                iterations: Some(50),
+                ..settings.budget
+            },
+            ..settings
+        };
+        let other = run(&games, &nrpa, &output.join("nrpa"), 1);
+        let compared = compare("nrpa", &results, &other);
+        assert!(compared.contains("Won 1 and 1 of 4 games, 3 and 3 moves in the 1 both won"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn names() {
+        let game = |filename: &str| TuneGame {
+            filename: filename.to_string(),
+            suits: 2,
+        };
+        let games = [
+            game("../examples/example7.game"),
+            game("../examples/1suits/example17.game"),
+            game("other/example17.game"),
+        ];
+        assert_eq!(
+            solution_names(&games),
+            vec![
+                "example7.game",
+                "examples_1suits_example17.game",
+                "other_example17.game"
+            ]
+        );
+        let games = collect(&["../examples/1suits/example17.game"], 2);
+        assert_eq!(games[0].suits, 1);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::deck::Deck;
+use crate::moves::Move;
+use crate::transposition::SeenEntry;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+// a position waiting in the A+ heap. The deck is played again from
+// the deal, so the moves that led there come back with it
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct HeapEntry {
+    pub moves: Vec<Move>,
+    pub depth: u32,
+    // what the beam search needed from there
+    pub estimate: u32,
+    pub total: u32,
+}
+
+impl HeapEntry {
+    pub fn deck(&self, deal: &Deck) -> Result<Deck, String> {
+        let mut deck = deal.clone();
+        deck.reset_moves();
+        for (i, m) in self.moves.iter().enumerate() {
+            deck.is_legal(m)
+                .map_err(|why| format!("Move {} ({}) is illegal: {}", i + 1, m.notation(), why))?;
+            deck = deck.apply_move(m);
+        }
+        Ok(deck)
+    }
+}
+
+// everything the --slow search needs to continue where it stopped
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub game: String,
+    pub cap: usize,
+    pub suits: usize,
+    pub seed: Option<u64>,
+    // after shuffling the unknown cards
+    pub deal: Deck,
+    pub best: usize,
+    // of the best solution so far, empty without one
+    pub best_moves: Vec<Move>,
+    pub heap: Vec<HeapEntry>,
+    pub seen: Vec<SeenEntry>,
+}
+
+impl Checkpoint {
+    // written next to the file first, so an interrupt while writing
+    // doesn't destroy the last checkpoint
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let tmp = format!("{}.tmp", path);
+        let file = File::create(&tmp).map_err(|why| format!("couldn't create {}: {}", tmp, why))?;
+        serde_json::to_writer(BufWriter::new(file), self)
+            .map_err(|why| format!("couldn't write {}: {}", tmp, why))?;
+        fs::rename(&tmp, path).map_err(|why| format!("couldn't rename {}: {}", tmp, why))
+    }
+
+    pub fn load(path: &str) -> Result<Checkpoint, String> {
+        let file = File::open(path).map_err(|why| format!("couldn't open {}: {}", path, why))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|why| format!("Invalid checkpoint {}: {}", path, why))
+    }
+}
+
+#[cfg(test)]
+mod checkpointtests {
+    use super::*;
+    use crate::deck::ENDGAME;
+    use crate::transposition::TranspositionTable;
+
+    #[test]
+    fn save_and_load() {
+        let deal = Deck::parse(&ENDGAME.to_string());
+        let next = deal.apply_move(&Move::regular(1, 0, 0));
+        let mut seen = TranspositionTable::new();
+        seen.insert(&deal, deal.hash(), 0);
+        seen.insert(&next, next.hash(), 1);
+        let checkpoint = Checkpoint {
+            game: "endgame.game".to_string(),
+            cap: 10,
+            suits: 1,
+            seed: Some(3),
+            deal: deal.clone(),
+            best: 3,
+            best_moves: vec![
+                Move::regular(1, 0, 0),
+                Move::regular(2, 0, 0),
+                Move::off(0, 0),
+            ],
+            heap: vec![HeapEntry {
+                moves: vec![Move::regular(1, 0, 0)],
+                depth: 1,
+                estimate: 2,
+                total: 3,
+            }],
+            seen: seen.entries(),
+        };
+        let path = std::env::temp_dir()
+            .join(format!("spider-checkpoint-{}", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        checkpoint.save(&path).expect("saved");
+        let loaded = Checkpoint::load(&path).expect("loaded");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.best_moves, checkpoint.best_moves);
+        assert_eq!(loaded.heap, checkpoint.heap);
+        let deck = loaded.heap[0].deck(&loaded.deal).expect("legal");
+        assert_eq!(deck.to_string(), next.to_string());
+        assert_eq!(deck.win_moves(), [Move::regular(1, 0, 0)]);
+        let mut restored = TranspositionTable::new();
+        restored.restore(&loaded.seen).expect("restored");
+        assert!(!restored.insert(&deck, deck.hash(), 1));
+        let wrong = HeapEntry {
+            moves: vec![Move::regular(1, 2, 0)],
+            depth: 1,
+            estimate: 2,
+            total: 3,
+        };
+        assert!(wrong.deck(&loaded.deal).is_err());
+        assert!(Checkpoint::load(&path).is_err());
+    }
+}
@@ -1,11 +1,18 @@
 use crate::card::Card;
+use crate::evaluator::{DefaultEvaluator, Evaluator, Score};
 use crate::moves::Move;
 use crate::pile::Pile;
+use crate::progress::{Observer, Progress};
+use crate::stats::{peak_memory_kb, DepthStats, SearchStats};
+use crate::transposition::TranspositionTable;
 use seahash;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 pub const MAX_MOVES: usize = 250;
 
@@ -16,59 +23,192 @@ pub struct Deck {
     off: Rc<Pile>,
     moves: [Move; MAX_MOVES],
     moves_index: usize,
+    // sums over the play piles, kept up to date by set_play
+    pile_chaos: u32,
+    playable: u32,
+    under: u32,
+    hidden: u32,
+    order: u8,
+    breaks: u32,
+    free_plays: u32,
+    free_talons: u32,
+    // how many play piles have a top card of the rank
+    top_ranks: [u8; 14],
+    // how many play piles need a top card of the rank to move their
+    // bottom card onto, and of those the ones with it on top themselves
+    wanted_ranks: [u8; 14],
+    wanted_self: [u8; 14],
+    // piles with a king at the bottom or one to move onto, each one
+    // chaos less
+    linked: u32,
+    chaos: u32,
+}
+
+// why get_moves left out a move the rules allow
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pruning {
+    Generated,
+    PlayableShortcut,
+    BrokenSequenceNotLonger,
+    SecondMoveToEmpty,
+    EndgameEmptyToEmpty,
+    EndgameSplitToEmpty,
+}
+
+impl fmt::Display for Pruning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Pruning::Generated => "generated",
+            Pruning::PlayableShortcut => "playable < 10 shortcut",
+            Pruning::BrokenSequenceNotLonger => "broken sequence not longer",
+            Pruning::SecondMoveToEmpty => "second move to empty column",
+            Pruning::EndgameEmptyToEmpty => "endgame empty-to-empty",
+            Pruning::EndgameSplitToEmpty => "endgame split to empty column",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+// why the rules don't allow a move in a position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IllegalMove {
+    NoSuchPile,
+    SamePile,
+    NoSuchCard,
+    FaceDown,
+    NotInSequence,
+    DoesNotFit,
+    NotAFullSuit,
+    TalonEmpty,
+    NotNextTalon,
+    EmptyColumn,
+    TooManyMoves,
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            IllegalMove::NoSuchPile => "there is no such pile",
+            IllegalMove::SamePile => "the cards would stay on their pile",
+            IllegalMove::NoSuchCard => "the pile doesn't have that many cards",
+            IllegalMove::FaceDown => "the card is face down",
+            IllegalMove::NotInSequence => "the cards above aren't a sequence of one suit",
+            IllegalMove::DoesNotFit => "the top card of the target isn't one rank higher",
+            IllegalMove::NotAFullSuit => "only a sequence from king to ace goes off",
+            IllegalMove::TalonEmpty => "the talon is dealt already",
+            IllegalMove::NotNextTalon => "another talon is dealt first",
+            IllegalMove::EmptyColumn => "talons are not dealt on empty piles",
+            IllegalMove::TooManyMoves => "the deck has no room for more moves",
+        };
+        write!(f, "{}", text)
+    }
 }
 
+// a deck is written as the text of a game file
+impl Serialize for Deck {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Deck {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Deck::try_parse(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+// the part of a deck that identifies a position in a search. As piles
+// are interned, comparing the pointers is enough to compare the cards -
+// as long as both decks were built since the last Pile::reset_tree
 #[derive(Clone)]
+pub struct Position {
+    hash: u64,
+    play: [Rc<Pile>; 10],
+    talon: [Rc<Pile>; 5],
+}
+
+impl Hash for Position {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl PartialEq for Position {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+            && self
+                .play
+                .iter()
+                .zip(other.play.iter())
+                .all(|(a, b)| Rc::ptr_eq(a, b))
+            && self
+                .talon
+                .iter()
+                .zip(other.talon.iter())
+                .all(|(a, b)| Rc::ptr_eq(a, b))
+    }
+}
+
+impl Eq for Position {}
+
+impl Position {
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // the play piles and then the talons as text
+    pub fn piles(&self) -> Vec<String> {
+        self.play
+            .iter()
+            .chain(self.talon.iter())
+            .map(|pile| pile.to_string())
+            .collect()
+    }
+
+    // the hash is taken again, it depends on how the piles were interned
+    pub fn parse(piles: &[String]) -> Result<Position, String> {
+        if piles.len() != 15 {
+            return Err(format!("Expected 15 piles, got {}", piles.len()));
+        }
+        let mut parsed = vec![];
+        for pile in piles {
+            parsed.push(Pile::parse(pile).ok_or(format!("Failed to parse {}", pile))?);
+        }
+        let mut deck = Deck::empty();
+        for (index, pile) in parsed.into_iter().enumerate() {
+            match index {
+                0..=9 => deck.set_play(index, pile),
+                _ => deck.set_talon(index - 10, pile),
+            }
+        }
+        Ok(deck.position(deck.hash()))
+    }
+}
+
 struct WeightedMove {
     deck: Rc<Deck>,
     talons: u32,
     hash: u64,
-    chaos: u32,
-    in_off: u32,
-    free_plays: u32,
-    playable: u32,
+    score: Score,
 }
 
 impl WeightedMove {
-    pub fn from(deck: Rc<Deck>, hash: u64) -> Self {
+    pub fn from<E: Evaluator + ?Sized>(deck: Rc<Deck>, hash: u64, evaluator: &E) -> Self {
         Self {
             talons: deck.free_talons(),
-            chaos: deck.chaos(),
-            hash: hash,
-            in_off: deck.in_off(),
-            free_plays: deck.free_plays(),
-            playable: deck.playable(),
-            deck: deck,
+            score: evaluator.score(&deck),
+            hash,
+            deck,
         }
     }
 }
 
 impl Ord for WeightedMove {
     fn cmp(&self, other: &Self) -> Ordering {
-        let ord = other.chaos.cmp(&self.chaos);
-        if ord != Ordering::Equal {
-            return ord;
-        }
-        let ready1 = self.playable + self.in_off + self.free_plays;
-        let ready2 = other.playable + other.in_off + other.free_plays;
-        let ord = ready1.cmp(&ready2);
-        if ord != Ordering::Equal {
-            return ord;
-        }
-        if self.chaos == 0 {
-            // once we are in straight win mode, we go differently
-            let ord = self.free_plays.cmp(&other.free_plays);
-            if ord != Ordering::Equal {
-                return ord;
-            }
-            // if the number of empty plays is equal, less in the off
-            // is actually a benefit (more strongly ordered)
-            let ord = other.in_off.cmp(&self.in_off);
-            if ord != Ordering::Equal {
-                return ord;
-            }
-        }
-        self.hash.cmp(&other.hash)
+        self.score
+            .cmp(&other.score)
+            .then(self.hash.cmp(&other.hash))
     }
 }
 
@@ -87,6 +227,15 @@ impl PartialOrd for WeightedMove {
     }
 }
 
+// the size of the beam's buckets
+fn frontier(unvisited: &[Vec<Rc<Deck>>; 6]) -> [usize; 6] {
+    let mut sizes = [0; 6];
+    for (size, bucket) in sizes.iter_mut().zip(unvisited.iter()) {
+        *size = bucket.len();
+    }
+    sizes
+}
+
 impl Deck {
     pub fn hash(&self) -> u64 {
         let mut state = seahash::State::new(
@@ -104,6 +253,18 @@ impl Deck {
         state.finalize()
     }
 
+    pub fn position(&self, hash: u64) -> Position {
+        Position {
+            hash,
+            play: self.play.clone(),
+            talon: self.talon.clone(),
+        }
+    }
+
+    pub fn off_count(&self) -> u32 {
+        self.off.count() as u32
+    }
+
     #[inline]
     pub fn in_off(&self) -> u32 {
         (self.off.count() as u32) * 13
@@ -115,13 +276,7 @@ impl Deck {
     }
 
     pub fn free_plays(&self) -> u32 {
-        let mut result = 0;
-        for i in 0..10 {
-            if self.play[i].count() == 0 {
-                result += 1;
-            }
-        }
-        result
+        self.free_plays
     }
 
     pub fn reset_moves(&mut self) {
@@ -129,14 +284,7 @@ impl Deck {
     }
 
     pub fn free_talons(&self) -> u32 {
-        // TODO: store as property
-        let mut ret = 0;
-        for i in 0..5 {
-            if self.talon[i].count() == 0 {
-                ret += 1
-            }
-        }
-        ret
+        self.free_talons
     }
 
     pub fn empty() -> Deck {
@@ -163,10 +311,28 @@ impl Deck {
             off: Pile::empty(),
             moves_index: 0,
             moves: [Move::invalid(); MAX_MOVES],
+            pile_chaos: 0,
+            playable: 0,
+            under: 0,
+            hidden: 0,
+            order: 0,
+            breaks: 0,
+            free_plays: 10,
+            free_talons: 5,
+            top_ranks: [0; 14],
+            wanted_ranks: [0; 14],
+            wanted_self: [0; 14],
+            linked: 0,
+            chaos: 0,
         }
     }
 
     pub fn parse(contents: &String) -> Deck {
+        Deck::try_parse(contents).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // for text that didn't come from a game file, e.g. a solution
+    pub fn try_parse(contents: &str) -> Result<Deck, String> {
         let mut newdeck = Deck::empty();
         // that should be enough :)
         let mut index = 0;
@@ -188,12 +354,12 @@ impl Deck {
                 Some(pile) => {
                     let parsed = Pile::parse(pile);
                     match parsed {
-                        None => panic!("Failed to parse {}", pile),
+                        None => return Err(format!("Failed to parse {}", pile)),
                         Some(pile) => match index {
-                            0..=9 => newdeck.play[index] = pile,
-                            10..=14 => newdeck.talon[index - 10] = pile,
-                            15 => newdeck.off = pile,
-                            _ => panic!("We went too far"),
+                            0..=9 => newdeck.set_play(index, pile),
+                            10..=14 => newdeck.set_talon(index - 10, pile),
+                            15 => newdeck.set_off(pile),
+                            _ => return Err("We went too far".to_string()),
                         },
                     }
                 }
@@ -202,16 +368,93 @@ impl Deck {
         }
 
         if index != 16 {
-            panic!("Not all piles are parsed");
+            return Err("Not all piles are parsed".to_string());
         }
-        newdeck
+        Ok(newdeck)
     }
 
+    // swaps the pile and updates the sums. The chaos only changes for
+    // the ranks the old and the new pile have on top or want
     pub fn set_play(&mut self, index: usize, pile: Rc<Pile>) {
+        let (old_top, old_wanted) = Deck::link_ranks(&self.play[index]);
+        let (top, wanted) = Deck::link_ranks(&pile);
+        // rank 0 stands for none
+        let ranks = (1 << old_top | 1 << old_wanted | 1 << top | 1 << wanted) & !1;
+        let before = self.rank_links(ranks);
+
+        let old = &self.play[index];
+        self.pile_chaos = self.pile_chaos - old.chaos() + pile.chaos();
+        self.playable = self.playable - old.playable() as u32 + pile.playable() as u32;
+        self.under = self.under - old.under() + pile.under();
+        self.hidden = self.hidden - old.hidden() + pile.hidden();
+        self.order = self.order - old.order() + pile.order();
+        self.breaks = self.breaks - old.breaks() + pile.breaks();
+        if old.is_empty() {
+            self.free_plays -= 1;
+        } else {
+            self.top_ranks[old_top] -= 1;
+            self.linked -= (old_wanted == 0) as u32;
+        }
+        if pile.is_empty() {
+            self.free_plays += 1;
+        } else {
+            self.top_ranks[top] += 1;
+            self.linked += (wanted == 0) as u32;
+        }
+        if old_wanted > 0 {
+            self.wanted_ranks[old_wanted] -= 1;
+            self.wanted_self[old_wanted] -= (old_top == old_wanted) as u8;
+        }
+        if wanted > 0 {
+            self.wanted_ranks[wanted] += 1;
+            self.wanted_self[wanted] += (top == wanted) as u8;
+        }
         self.play[index] = pile;
+
+        self.linked = self.linked + self.rank_links(ranks) - before;
+        // per non-empty pile the chaos is at minimum 1, but if the
+        // pile is connected, we substract one. Obvious wins are 0
+        self.chaos = (self.pile_chaos - self.linked).saturating_sub(self.free_plays);
+    }
+
+    // the rank of the top card and the one the bottom card wants to
+    // move onto, 0 for none. Kings at the bottom want nothing
+    fn link_ranks(pile: &Pile) -> (usize, usize) {
+        if pile.is_empty() {
+            return (0, 0);
+        }
+        let top = pile.at(pile.count() - 1).rank() as usize;
+        match pile.at(0).rank() {
+            13 => (top, 0),
+            bottom => (top, bottom as usize + 1),
+        }
+    }
+
+    // the piles wanting one of the ranks that find it on another pile,
+    // the ranks as bits
+    fn rank_links(&self, mut ranks: u32) -> u32 {
+        let mut links = 0;
+        while ranks != 0 {
+            let rank = ranks.trailing_zeros() as usize;
+            ranks &= ranks - 1;
+            let wanted = self.wanted_ranks[rank] as u32;
+            links += match self.top_ranks[rank] {
+                0 => 0,
+                // a pile can't connect to itself
+                1 => wanted - self.wanted_self[rank] as u32,
+                _ => wanted,
+            };
+        }
+        links
     }
 
     pub fn set_talon(&mut self, index: usize, pile: Rc<Pile>) {
+        if self.talon[index].is_empty() {
+            self.free_talons -= 1;
+        }
+        if pile.is_empty() {
+            self.free_talons += 1;
+        }
         self.talon[index] = pile;
     }
 
@@ -238,7 +481,15 @@ impl Deck {
         result
     }
 
-    fn next_talon(&self) -> Option<usize> {
+    pub fn pile_count(&self, index: usize) -> usize {
+        self.play[index].count()
+    }
+
+    pub fn pile(&self, index: usize) -> &Pile {
+        &self.play[index]
+    }
+
+    pub fn next_talon(&self) -> Option<usize> {
         for i in 0..5 {
             if !self.talon[i].is_empty() {
                 return Some(i);
@@ -248,6 +499,11 @@ impl Deck {
     }
 
     pub fn get_moves(&self, vec: &mut Vec<Move>) {
+        self.generate_moves(vec, |_, _| {});
+    }
+
+    // get_moves, telling pruned about every move it leaves out
+    fn generate_moves<F: FnMut(Move, Pruning)>(&self, vec: &mut Vec<Move>, mut pruned: F) {
         vec.clear();
 
         if self.moves_index == MAX_MOVES - 1 {
@@ -329,6 +585,10 @@ impl Deck {
                             if to_pile.sequence_of(top_suit) + broken_sequence
                                 <= from_pile.sequence_of(top_suit)
                             {
+                                pruned(
+                                    Move::regular(from, to, index),
+                                    Pruning::BrokenSequenceNotLonger,
+                                );
                                 continue;
                             }
                         }
@@ -336,6 +596,7 @@ impl Deck {
                         // if there is a talon left to draw the empty cell
                         // we move to does matter. In the endgame not at all
                         if next_talon.is_none() {
+                            pruned(Move::regular(from, to, index), Pruning::SecondMoveToEmpty);
                             continue;
                         }
                     } else {
@@ -344,10 +605,18 @@ impl Deck {
                         if next_talon.is_none() {
                             if index == 0 {
                                 // forbid moves between empty cells once the talons are gone
+                                pruned(
+                                    Move::regular(from, to, index),
+                                    Pruning::EndgameEmptyToEmpty,
+                                );
                                 continue;
                             }
                             // there is no plausible reason to split up sequences in end game
                             if broken_sequence > 0 {
+                                pruned(
+                                    Move::regular(from, to, index),
+                                    Pruning::EndgameSplitToEmpty,
+                                );
                                 continue;
                             }
                         }
@@ -368,6 +637,73 @@ impl Deck {
         }
     }
 
+    // every move the rules allow, without the pruning of get_moves.
+    // Only a finished suit is still moved off right away, as the game
+    // does it on its own
+    pub fn get_all_moves(&self, vec: &mut Vec<Move>) {
+        vec.clear();
+
+        if self.moves_index == MAX_MOVES - 1 {
+            return;
+        }
+        let next_talon = self.next_talon();
+        let mut one_is_empty = false;
+
+        for from in 0..10 {
+            let from_pile = &self.play[from];
+            if from_pile.is_empty() {
+                one_is_empty = true;
+                continue;
+            }
+            let top_count = from_pile.top_sequence_length();
+            if top_count == 13 {
+                vec.clear();
+                vec.push(Move::off(from, from_pile.count() - 13));
+                return;
+            }
+            for index in from_pile.count() - top_count..from_pile.count() {
+                let rank = from_pile.at(index).rank();
+                for to in 0..10 {
+                    if to == from {
+                        continue;
+                    }
+                    let to_pile = &self.play[to];
+                    if to_pile.is_empty() || to_pile.at(to_pile.count() - 1).rank() == rank + 1 {
+                        vec.push(Move::regular(from, to, index));
+                    }
+                }
+            }
+        }
+
+        if let Some(next_talon) = next_talon {
+            if !one_is_empty {
+                vec.push(Move::from_talon(next_talon));
+            }
+        }
+    }
+
+    // every move the rules allow, with what get_moves made of it
+    pub fn explain_pruning(&self) -> Vec<(Move, Pruning)> {
+        let mut all = vec![];
+        self.get_all_moves(&mut all);
+        let mut generated = vec![];
+        let mut reasons = vec![];
+        self.generate_moves(&mut generated, |m, why| reasons.push((m, why)));
+        let shortcut = self.next_talon().is_some() && self.playable() < 10;
+        all.into_iter()
+            .map(|m| {
+                if generated.contains(&m) {
+                    (m, Pruning::Generated)
+                } else if shortcut {
+                    (m, Pruning::PlayableShortcut)
+                } else {
+                    let reason = reasons.iter().find(|(r, _)| *r == m);
+                    (m, reason.expect("every pruned move has a reason").1)
+                }
+            })
+            .collect()
+    }
+
     pub fn explain_move(&self, m: &Move) -> String {
         if m.is_talon() {
             return format!("Draw another talon");
@@ -444,81 +780,35 @@ impl Deck {
     }
 
     pub fn pile_chaos(&self) -> u32 {
-        let mut result = 0;
-        for i in 0..10 {
-            result += self.play[i].chaos();
-        }
-        result
+        self.pile_chaos
     }
 
     pub fn hidden(&self) -> u32 {
-        let mut result = 0;
-        for i in 0..10 {
-            result += self.play[i].hidden();
-        }
-        result
+        self.hidden
     }
 
     pub fn order(&self) -> u8 {
-        let mut result = 0;
-        for i in 0..10 {
-            result += self.play[i].order();
-        }
-        result
+        self.order
     }
 
     pub fn chaos(&self) -> u32 {
-        // first sum up inner pile chaos
-        let mut result = self.pile_chaos();
-        // per non-empty pile the chaos is at minimum 1
-        // but if the pile is connected, we substract one
-        // obvious wins are chaos 0
-        for i in 0..10 {
-            if self.play[i].count() == 0 {
-                continue;
-            }
-            let c1 = self.play[i].at(0);
-            if c1.rank() == 13 {
-                result -= 1;
-                continue;
-            }
-            for j in 0..10 {
-                if j == i {
-                    continue;
-                }
-                let jpile = &self.play[j];
-                if jpile.count() == 0 {
-                    continue;
-                }
-                // we don't need the suit here
-                if c1.rank() == jpile.at(jpile.count() - 1).rank() - 1 {
-                    result -= 1;
-                    break;
-                }
-            }
-        }
-        let mut fp = self.free_plays();
-        while fp > 0 && result > 0 {
-            fp -= 1;
-            result -= 1;
-        }
-        result
+        self.chaos
+    }
+
+    // a guaranteed minimum of the moves to win: every break in the
+    // piles takes a move to resolve, every suit missing in the off a
+    // move to get there and every talon left a deal. No move makes
+    // more than one of them go away
+    pub fn lower_bound(&self) -> u32 {
+        self.breaks + (8 - self.off_count()) + (5 - self.free_talons)
     }
 
     pub fn playable(&self) -> u32 {
-        let mut result: u32 = 0;
-        for i in 0..10 {
-            result += self.play[i].playable() as u32;
-        }
-        result
+        self.playable
     }
 
     pub fn under(&self) -> u32 {
-        let mut result: u32 = 0;
-        for i in 0..10 {
-            result += self.play[i].under();
-        }
-        result
+        self.under
     }
 
     // how many talon cards will fall into sequence
@@ -582,6 +872,65 @@ impl Deck {
         newdeck
     }
 
+    pub fn is_legal(&self, m: &Move) -> Result<(), IllegalMove> {
+        if self.moves_index >= MAX_MOVES {
+            return Err(IllegalMove::TooManyMoves);
+        }
+        if m.is_talon() {
+            if m.from() >= 5 {
+                return Err(IllegalMove::NoSuchPile);
+            }
+            if self.talon[m.from()].is_empty() {
+                return Err(IllegalMove::TalonEmpty);
+            }
+            if self.next_talon() != Some(m.from()) {
+                return Err(IllegalMove::NotNextTalon);
+            }
+            if self.play.iter().any(|pile| pile.is_empty()) {
+                return Err(IllegalMove::EmptyColumn);
+            }
+            return Ok(());
+        }
+        if m.from() >= 10 || (!m.is_off() && m.to() >= 10) {
+            return Err(IllegalMove::NoSuchPile);
+        }
+        let from_pile = &self.play[m.from()];
+        if m.index() >= from_pile.count() {
+            return Err(IllegalMove::NoSuchCard);
+        }
+        if !from_pile.at(m.index()).faceup() {
+            return Err(IllegalMove::FaceDown);
+        }
+        let moved = from_pile.count() - m.index();
+        if moved > from_pile.top_sequence_length() {
+            return Err(IllegalMove::NotInSequence);
+        }
+        if m.is_off() {
+            if moved != 13 {
+                return Err(IllegalMove::NotAFullSuit);
+            }
+            return Ok(());
+        }
+        if m.from() == m.to() {
+            return Err(IllegalMove::SamePile);
+        }
+        let to_pile = &self.play[m.to()];
+        if !to_pile.is_empty()
+            && !from_pile
+                .at(m.index())
+                .fits_on_top(&to_pile.at(to_pile.count() - 1))
+        {
+            return Err(IllegalMove::DoesNotFit);
+        }
+        Ok(())
+    }
+
+    // apply_move for moves that don't come from a generator
+    pub fn try_apply_move(&self, m: &Move) -> Result<Deck, IllegalMove> {
+        self.is_legal(m)?;
+        Ok(self.apply_move(m))
+    }
+
     pub fn shortest_pile(&self) -> usize {
         let mut result = self.play[0].count();
         for i in 1..10 {
@@ -622,7 +971,17 @@ impl Deck {
         cards
     }
 
-    pub fn shuffle_unknowns(&mut self, n_suits: usize) {
+    // a different shuffle every second, for when no seed was given.
+    // Resolved once, so it can be written along with the solution
+    pub fn time_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    // the same seed gives the same cards to the unknowns
+    pub fn shuffle_unknowns_with_seed(&mut self, n_suits: usize, seed: u64) {
         let mut cards = Deck::full_deck(n_suits);
         for i in 0..10 {
             self.play[i].remove_known(&mut cards);
@@ -643,13 +1002,7 @@ impl Deck {
             }
         }
         if !cards.is_empty() {
-            Card::shuffle(
-                &mut cards,
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs(),
-            );
+            Card::shuffle(&mut cards, seed);
             //println!("Cards {}", Card::vec_as_string(&cards));
         }
         for i in 0..10 {
@@ -663,33 +1016,108 @@ impl Deck {
         }
     }
 
+    // the tests' shorthand for the default ordering and a fresh table
+    #[cfg(test)]
     pub fn shortest_path(
         &mut self,
         cap: usize,
         debug: bool,
         won_decks: Option<HashSet<u64>>,
+    ) -> Option<i32> {
+        let mut seen = TranspositionTable::new();
+        self.shortest_path_with(&DefaultEvaluator, cap, debug, won_decks, &mut seen)
+    }
+
+    // like shortest_path, but the caller picks the ordering of the beam
+    // and provides (and can inspect) the table of positions seen
+    pub fn shortest_path_with<E: Evaluator + ?Sized>(
+        &mut self,
+        evaluator: &E,
+        cap: usize,
+        debug: bool,
+        won_decks: Option<HashSet<u64>>,
+        seen: &mut TranspositionTable,
+    ) -> Option<i32> {
+        self.shortest_path_with_stats(
+            evaluator,
+            cap,
+            debug,
+            won_decks,
+            seen,
+            &mut SearchStats::default(),
+        )
+    }
+
+    // like shortest_path_with, and counts what the search did
+    pub fn shortest_path_with_stats<E: Evaluator + ?Sized>(
+        &mut self,
+        evaluator: &E,
+        cap: usize,
+        debug: bool,
+        won_decks: Option<HashSet<u64>>,
+        seen: &mut TranspositionTable,
+        stats: &mut SearchStats,
+    ) -> Option<i32> {
+        let mut observer = Observer::default();
+        let result =
+            self.shortest_path_observed(evaluator, cap, debug, won_decks, seen, &mut observer);
+        *stats = observer.stats;
+        result
+    }
+
+    // reports the progress after every depth and stops once cancelled.
+    // Then the result is None and the moves lead to the most promising
+    // position of the last depth instead of a win
+    pub fn shortest_path_observed<E: Evaluator + ?Sized>(
+        &mut self,
+        evaluator: &E,
+        cap: usize,
+        debug: bool,
+        won_decks: Option<HashSet<u64>>,
+        seen: &mut TranspositionTable,
+        observer: &mut Observer,
+    ) -> Option<i32> {
+        let start = Instant::now();
+        let result = self.beam_search(evaluator, cap, debug, won_decks, seen, observer);
+        observer.stats.interned_piles = Pile::interned();
+        observer.stats.peak_memory_kb = peak_memory_kb();
+        observer.stats.seconds = start.elapsed().as_secs_f64();
+        result
+    }
+
+    fn beam_search<E: Evaluator + ?Sized>(
+        &mut self,
+        evaluator: &E,
+        cap: usize,
+        debug: bool,
+        won_decks: Option<HashSet<u64>>,
+        seen: &mut TranspositionTable,
+        observer: &mut Observer,
     ) -> Option<i32> {
         let mut unvisited: [Vec<Rc<Deck>>; 6] = Default::default();
         unvisited[self.free_talons() as usize].push(Rc::new(self.clone()));
         // sort only the index
         let mut new_unvisited: Vec<WeightedMove> = Vec::new();
-        let mut seen = HashSet::new();
 
         let mut depth: i32 = 0;
         let mut moves = vec![];
 
         loop {
+            let depth_start = Instant::now();
             for i in 0..=5 {
                 for deck in &unvisited[i] {
                     // reuse moves vector
                     deck.get_moves(&mut moves);
+                    observer.stats.expanded += 1;
+                    observer.stats.generated += moves.len();
 
                     for m in &moves {
                         let newdeck = Rc::new(deck.apply_move(m));
                         let hash = newdeck.hash();
-                        if !seen.contains(&hash) {
-                            new_unvisited.push(WeightedMove::from(newdeck, hash));
-                            seen.insert(hash);
+                        if seen.insert(&newdeck, hash, depth as u32 + 1) {
+                            new_unvisited.push(WeightedMove::from(newdeck, hash, evaluator));
+                        } else {
+                            observer.stats.duplicates += 1;
                         }
                     }
                 }
@@ -706,6 +1134,18 @@ impl Deck {
             loop {
                 if let Some(wm) = iterator.next() {
                     if wm.deck.is_won() {
+                        observer.stats.depths.push(DepthStats {
+                            depth: depth as usize,
+                            candidates: new_unvisited.len(),
+                            frontier: frontier(&unvisited),
+                            seconds: depth_start.elapsed().as_secs_f64(),
+                        });
+                        // the first win is at the shallowest depth
+                        observer.report(Progress {
+                            depth: depth as usize,
+                            frontier: unvisited.iter().map(|bucket| bucket.len()).sum(),
+                            best: Some(depth as usize + 1),
+                        });
                         self.moves = wm.deck.moves.clone();
                         self.moves_index = wm.deck.moves_index;
                         return Some(depth + 1);
@@ -764,13 +1204,39 @@ impl Deck {
                             count += 1;
                         }
             */
+            observer.stats.depths.push(DepthStats {
+                depth: depth as usize,
+                candidates: new_unvisited.len(),
+                frontier: frontier(&unvisited),
+                seconds: depth_start.elapsed().as_secs_f64(),
+            });
+            observer.report(Progress {
+                depth: depth as usize,
+                frontier: unvisited.iter().map(|bucket| bucket.len()).sum(),
+                best: None,
+            });
+            if observer.is_cancelled() {
+                // the most promising position stands in for the win
+                if let Some(wm) = new_unvisited.last() {
+                    self.moves = wm.deck.moves;
+                    self.moves_index = wm.deck.moves_index;
+                }
+                observer.stats.cancelled = true;
+                return None;
+            }
             new_unvisited.clear();
             depth += 1;
+            seen.age(depth as u32 + 1);
         }
 
         Some(-1 * depth)
     }
 
+    // false if some cards were only guessed by shuffle_unknowns_with_seed
+    pub fn is_fully_known(&self) -> bool {
+        !self.play.iter().any(|p| p.has_unknown()) && !self.talon.iter().any(|p| p.has_unknown())
+    }
+
     pub fn top_card_unknown(&self, index: usize) -> bool {
         let pile = &self.play[index];
         if pile.count() == 0 {
@@ -783,14 +1249,14 @@ impl Deck {
         let mut c = Card::new(c.value());
         c.set_faceup(self.play[play].at(index).faceup());
         let new = self.play[play].replace_at(index, &c);
-        self.play[play] = new;
+        self.set_play(play, new);
     }
 
     // for test cases
     #[allow(dead_code)]
     pub fn compare(&self, other: &Deck) -> Ordering {
-        let m1 = WeightedMove::from(Rc::new(self.clone()), self.hash());
-        let m2 = WeightedMove::from(Rc::new(other.clone()), other.hash());
+        let m1 = WeightedMove::from(Rc::new(self.clone()), self.hash(), &DefaultEvaluator);
+        let m2 = WeightedMove::from(Rc::new(other.clone()), other.hash(), &DefaultEvaluator);
         m1.cmp(&m2)
     }
 }
@@ -872,9 +1338,50 @@ impl Ord for DeltaMove {
     }
 }
 
+// a suit of hearts three moves from the win, shared by the tests of
+// the other modules
+#[cfg(test)]
+pub const ENDGAME: &str = "Play0: KH QH JH TH
+Play1: 9H
+Play2: 8H 7H 6H 5H 4H 3H 2H AH
+Play3:
+Play4:
+Play5:
+Play6:
+Play7:
+Play8:
+Play9:
+Deal0:
+Deal1:
+Deal2:
+Deal3:
+Deal4:
+Off: KS KS KS KS KH KH KH";
+
+// nine moves from the win at the shortest, far more for the
+// complete move generator
+#[cfg(test)]
+pub const NINE_MOVES: &str = "Play0:
+Play1: QH..AH
+Play2:
+Play3: KS
+Play4:
+Play5: 9S
+Play6: |7S |QS KH
+Play7: |8S JS..TS
+Play8: 6S..AS
+Play9:
+Deal0:
+Deal1:
+Deal2:
+Deal3:
+Deal4:
+Off: KS KH KH KS KH KS";
+
 #[cfg(test)]
 mod decktests {
     use super::*;
+    use crate::progress::Cancel;
 
     #[test]
     fn parse() {
@@ -1030,6 +1537,191 @@ Off: KS KH";
         }
     }
 
+    #[test]
+    fn complete_moves() {
+        let text = "Play0: KS QS JS TS 9S 8S 7S 6S
+Play1: QS
+Play2: |KS |2S |JS |KS |JH QH 2H AH TH
+Play3: |4H 3H 5H 6H
+Play4: |TH |3S |TS 9S 8S KH QH JH TS 9H 8H 7H 6S 5S 4S 3S 2S AS
+Play5: |7H |9H 8H 9S
+Play6: |7S |KH |AH |4H 2H 3H
+Play7: |JS |7S 6S 5H 4H 2S KH QS 6H 5S 4S 3S 8S AS
+Play8: 6S 5S 4S 3H 2H AH
+Play9: QH JH TH 9H 8H 7H 6H 5H
+Deal0:
+Deal1:
+Deal2:
+Deal3:
+Deal4:
+Off: KS KH";
+        let deck = Deck::parse(&text.to_string());
+        let mut pruned = vec![];
+        deck.get_moves(&mut pruned);
+        let mut all = vec![];
+        deck.get_all_moves(&mut all);
+        for m in &pruned {
+            assert!(all.contains(m));
+        }
+        // splitting 4S..AS off onto 5H, the beam doesn't consider that
+        assert!(all.contains(&Move::regular(4, 9, 14)));
+        assert!(!pruned.contains(&Move::regular(4, 9, 14)));
+        assert_eq!(all.len(), 14);
+
+        let text = "Play0: |3S |5H |AS |7H |4S JH
+        Play1: |AS |3S |TH |AH |9S TS
+        Play2: |AH |4H |5S |JS |3S 3H
+        Play3: |TH |4S |QH |TS |2H QS
+        Play4: |QH |3S |2S |AH 9H
+        Play5: |6S |8S |4H |7S 3H
+        Play6: |TH |QS |AS |5S 9H
+        Play7: |KS |KH |2S |6S TS
+        Play8: |JS |AH |8H |KS 7S
+        Play9: |7H |6S |3H |KS 4H
+        Deal0: 4S 9H 5H 7H 5H TH JH JH 6H JH
+        Deal1: JS 2S QH 6H 6H 7S JS QS 8H 4S
+        Deal2: 2H 5S 8S 3H 2H 8S KS 6H 9S KH
+        Deal3: |9S |2S |8H |KH |TS |AS |6S |9H |5H |7H
+        Deal4: |5S |2H |8H |7S |4H |9S |QH |8S |QS |KH
+        Off:";
+        let deck = Deck::parse(&text.to_string());
+        deck.get_moves(&mut pruned);
+        deck.get_all_moves(&mut all);
+        // no sequences to split and no empty piles yet
+        assert_eq!(all, pruned);
+    }
+
+    #[test]
+    fn explain_pruning() {
+        let text = "Play0: KS QS JS TS 9S 8S 7S 6S
+Play1: QS
+Play2: |KS |2S |JS |KS |JH QH 2H AH TH
+Play3: |4H 3H 5H 6H
+Play4: |TH |3S |TS 9S 8S KH QH JH TS 9H 8H 7H 6S 5S 4S 3S 2S AS
+Play5: |7H |9H 8H 9S
+Play6: |7S |KH |AH |4H 2H 3H
+Play7: |JS |7S 6S 5H 4H 2S KH QS 6H 5S 4S 3S 8S AS
+Play8: 6S 5S 4S 3H 2H AH
+Play9: QH JH TH 9H 8H 7H 6H 5H
+Deal0:
+Deal1:
+Deal2:
+Deal3:
+Deal4:
+Off: KS KH";
+        let deck = Deck::parse(&text.to_string());
+        let explained = deck.explain_pruning();
+        assert_eq!(explained.len(), 14);
+        let reason = |m: Move| explained.iter().find(|(e, _)| *e == m).unwrap().1;
+        assert_eq!(reason(Move::regular(4, 0, 13)), Pruning::Generated);
+        assert_eq!(reason(Move::regular(5, 2, 3)), Pruning::Generated);
+        assert_eq!(
+            reason(Move::regular(4, 9, 14)),
+            Pruning::BrokenSequenceNotLonger
+        );
+        assert_eq!(
+            explained
+                .iter()
+                .filter(|(_, why)| *why == Pruning::Generated)
+                .count(),
+            2
+        );
+
+        let text = "Play0: 7H 6H 5H AS
+        Play1:
+        Play2: KS
+        Play3: TH
+        Play4: |3S |9S |TS TH
+        Play5: |9S |9H 8H 4S
+        Play6: |7S |QS |KH |4H 3H 2S QH JH KH QH JS QS KS
+        Play7: |8S |JS |6S 7S
+        Play8: |6S |8S |AH |5S 4H 3H 2H AH
+        Play9: 5H 2H JH TS 9H 8H 7H 6H 5S 4S 3S 2S AS
+        Deal0:
+        Deal1:
+        Deal2:
+        Deal3:
+        Deal4:
+        Off: KS KH KH KS";
+        let deck = Deck::parse(&text.to_string());
+        let explained = deck.explain_pruning();
+        // KS alone to the empty pile
+        assert!(explained.contains(&(Move::regular(2, 1, 0), Pruning::EndgameEmptyToEmpty)));
+        assert_eq!(
+            Pruning::PlayableShortcut.to_string(),
+            "playable < 10 shortcut"
+        );
+    }
+
+    #[test]
+    fn legality() {
+        let text = "Play0: 7H 6H 5H AS
+        Play1:
+        Play2: KS
+        Play3: TH
+        Play4: |3S |9S |TS TH
+        Play5: |9S |9H 8H 4S
+        Play6: |7S |QS |KH |4H 3H 2S QH JH KH QH JS QS KS
+        Play7: |8S |JS |6S 7S
+        Play8: |6S |8S |AH |5S 4H 3H 2H AH
+        Play9: 5H 2H JH TS 9H 8H 7H 6H 5S 4S 3S 2S AS
+        Deal0: 4S 9H 5H 7H 5H TH JH JH 6H JH
+        Deal1:
+        Deal2:
+        Deal3:
+        Deal4:
+        Off: KS KH KH KS";
+        let deck = Deck::parse(&text.to_string());
+        let mut moves = vec![];
+        deck.get_all_moves(&mut moves);
+        for m in &moves {
+            assert_eq!(deck.is_legal(m), Ok(()));
+        }
+        assert_eq!(deck.is_legal(&Move::regular(0, 1, 3)), Ok(()));
+        assert_eq!(
+            deck.is_legal(&Move::regular(0, 1, 1)),
+            Err(IllegalMove::NotInSequence)
+        );
+        assert_eq!(
+            deck.is_legal(&Move::regular(4, 1, 1)),
+            Err(IllegalMove::FaceDown)
+        );
+        assert_eq!(
+            deck.is_legal(&Move::regular(3, 2, 0)),
+            Err(IllegalMove::DoesNotFit)
+        );
+        assert_eq!(
+            deck.is_legal(&Move::regular(3, 3, 0)),
+            Err(IllegalMove::SamePile)
+        );
+        assert_eq!(
+            deck.is_legal(&Move::regular(2, 12, 0)),
+            Err(IllegalMove::NoSuchPile)
+        );
+        assert_eq!(
+            deck.is_legal(&Move::regular(2, 1, 1)),
+            Err(IllegalMove::NoSuchCard)
+        );
+        assert_eq!(
+            deck.is_legal(&Move::off(9, 8)),
+            Err(IllegalMove::NotAFullSuit)
+        );
+        assert_eq!(
+            deck.is_legal(&Move::from_talon(0)),
+            Err(IllegalMove::EmptyColumn)
+        );
+        assert_eq!(
+            deck.is_legal(&Move::from_talon(1)),
+            Err(IllegalMove::TalonEmpty)
+        );
+        let deck = deck.try_apply_move(&Move::regular(0, 1, 3)).expect("legal");
+        assert_eq!(deck.is_legal(&Move::from_talon(0)), Ok(()));
+        assert_eq!(
+            deck.try_apply_move(&Move::regular(0, 1, 3)).err(),
+            Some(IllegalMove::NoSuchCard)
+        );
+    }
+
     #[test]
     fn pick_good_ones() {
         let text = "Play0: AS
@@ -1151,6 +1843,61 @@ Off: KS KS KS KS KH KH KH";
         Off: KS KS KS KS KH KH KH";
         let mut deck = Deck::parse(&text.to_string());
         assert_eq!(deck.shortest_path(10, false, None).expect("winnable"), 3);
+        let mut stats = SearchStats::default();
+        let result = deck.shortest_path_with_stats(
+            &DefaultEvaluator,
+            10,
+            false,
+            None,
+            &mut TranspositionTable::new(),
+            &mut stats,
+        );
+        assert_eq!(result, Some(3));
+        // one entry per depth, the won one included
+        assert_eq!(stats.depths.len(), 3);
+        assert!(stats.expanded >= 3);
+        assert_eq!(
+            stats.generated,
+            stats.duplicates + stats.depths.iter().map(|d| d.candidates).sum::<usize>()
+        );
+        assert!(stats.interned_piles > 0);
+
+        deck.reset_moves();
+        let cancel = Cancel::new();
+        cancel.cancel();
+        let mut depths = vec![];
+        let mut observer = Observer::new(cancel);
+        observer.progress = Some(Box::new(|progress: &Progress| depths.push(progress.depth)));
+        let result = deck.shortest_path_observed(
+            &DefaultEvaluator,
+            10,
+            false,
+            None,
+            &mut TranspositionTable::new(),
+            &mut observer,
+        );
+        // stopped after the first depth, with the best move so far
+        assert_eq!(result, None);
+        assert!(observer.stats.cancelled);
+        assert_eq!(deck.win_moves().len(), 1);
+        drop(observer);
+        assert_eq!(depths, [0]);
+
+        // the win is reported along with its depth
+        deck.reset_moves();
+        let mut reported = vec![];
+        let mut observer = Observer::new(Cancel::new());
+        observer.progress = Some(Box::new(|progress: &Progress| reported.push(progress.best)));
+        deck.shortest_path_observed(
+            &DefaultEvaluator,
+            10,
+            false,
+            None,
+            &mut TranspositionTable::new(),
+            &mut observer,
+        );
+        drop(observer);
+        assert_eq!(reported, [None, None, Some(3)]);
     }
 
     #[test]
@@ -1239,6 +1986,16 @@ Off: KS KS KS KS KH KH KH";
         assert_eq!(res.expect("out of options"), -2);
     }
 
+    #[test]
+    fn beam_length() {
+        // the length depends only on the positions, so a change in it
+        // is a change in the search
+        let text = std::fs::read_to_string("../examples/example10.game").expect("example10");
+        let mut deck = Deck::parse(&text);
+        assert!(deck.shortest_path(200, false, None).expect("won") > 0);
+        assert_eq!(deck.win_moves().len(), 119);
+    }
+
     #[test]
     fn top_card_unknown() {
         let text = "Play0: JS TS 9S 8S 7S 6S 5S 4S AS TH 9H 8H 7H 6H 5H 4H 3H 2H AH
@@ -1419,6 +2176,149 @@ Off: KH KS KH KS";
         //assert_eq!(deck2.compare(&deck1), Ordering::Greater);
     }
 
+    // the aggregates as they were computed before they got cached
+    fn check_aggregates(deck: &Deck) {
+        let piles = deck.play.iter();
+        assert_eq!(
+            deck.pile_chaos(),
+            piles.clone().map(|p| p.chaos()).sum::<u32>()
+        );
+        assert_eq!(
+            deck.playable(),
+            piles.clone().map(|p| p.playable() as u32).sum::<u32>()
+        );
+        assert_eq!(deck.under(), piles.clone().map(|p| p.under()).sum::<u32>());
+        assert_eq!(
+            deck.hidden(),
+            piles.clone().map(|p| p.hidden()).sum::<u32>()
+        );
+        assert_eq!(deck.order(), piles.clone().map(|p| p.order()).sum::<u8>());
+        assert_eq!(deck.breaks, piles.clone().map(|p| p.breaks()).sum::<u32>());
+        let free_plays = piles.clone().filter(|p| p.is_empty()).count() as u32;
+        assert_eq!(deck.free_plays(), free_plays);
+        assert_eq!(
+            deck.free_talons(),
+            deck.talon.iter().filter(|p| p.is_empty()).count() as u32
+        );
+
+        let mut chaos = deck.pile_chaos();
+        for i in 0..10 {
+            if deck.play[i].count() == 0 {
+                continue;
+            }
+            let c1 = deck.play[i].at(0);
+            if c1.rank() == 13 {
+                chaos -= 1;
+                continue;
+            }
+            for j in 0..10 {
+                let jpile = &deck.play[j];
+                if j == i || jpile.count() == 0 {
+                    continue;
+                }
+                if c1.rank() + 1 == jpile.at(jpile.count() - 1).rank() {
+                    chaos -= 1;
+                    break;
+                }
+            }
+        }
+        assert_eq!(deck.chaos(), chaos.saturating_sub(free_plays));
+    }
+
+    #[test]
+    fn cached_aggregates() {
+        let text = "Play0: |TS |6S |5H |9H |8H JH..8H 9H
+                    Play1: JS..TS KH
+                    Play2: |QH |JH |4S |3H |AH 5H 4S..3S 9H QS
+                    Play3: |4H |8S |8S |2H 4H..3H 2S JS
+                    Play4: |QH |4H |6S |AH 6H 4S 3H..2H
+                    Play5: |6H |7H |TH |AS 7S KS KS..QS
+                    Play6: |6S |TH |8S 2H..AH JH 2S
+                    Play7: |AS |2S |9S KS KH 5H
+                    Play8: |7S |QH |JS |2H KS 7H 8S..4S
+                    Play9: |QS |3H 7H
+                    Deal0: 
+                    Deal1: 
+                    Deal2: |5H |7H |TH |TS |3S |5S |QS |7S |KH |KH
+                    Deal3: |AS |3S |5S |8H |QH |JS |4H |6H |AH |6H
+                    Deal4: |9S |5S |3S |AS |JH |9S |8H |9S |TS |2S
+                    Off:";
+        let mut deck = Deck::parse(&text.to_string());
+        check_aggregates(&deck);
+        // chaos is updated from the two touched piles only
+        let mut moves = vec![];
+        deck.get_all_moves(&mut moves);
+        for m in &moves {
+            check_aggregates(&deck.apply_move(m));
+        }
+        deck = deck.apply_move(&Move::regular(4, 8, 6));
+        check_aggregates(&deck);
+        deck = deck.apply_move(&Move::from_talon(2));
+        check_aggregates(&deck);
+        deck = deck.apply_move(&Move::regular(1, 5, 0));
+        check_aggregates(&deck);
+
+        // walk through a whole game including off moves
+        let text = "Play0: TH 9H 8H 7H 6H 5H 4H 3H
+        Play1: 7S
+        Play2: KS
+        Play3: TH 9S
+        Play4: JS
+        Play5: 
+        Play6: |AS |QS |KH |4H 3H 2S QH JH KH QH
+        Play7: 2H AH
+        Play8: |6S |8S AH
+        Play9: 5H 2H JH TS 9H 8H 7H 6H 5S 4S 3S
+        Deal0: 
+        Deal1: 
+        Deal2: 
+        Deal3: 
+        Deal4: 
+        Off: KS KH KH KS KS";
+        let mut deck = Deck::parse(&text.to_string());
+        deck.shortest_path_with(
+            &DefaultEvaluator,
+            20,
+            false,
+            None,
+            &mut TranspositionTable::new(),
+        )
+        .expect("winnable");
+        let moves = deck.win_moves();
+        deck.reset_moves();
+        check_aggregates(&deck);
+        for m in moves {
+            deck = deck.apply_move(&m);
+            check_aggregates(&deck);
+        }
+        assert!(deck.is_won());
+    }
+
+    #[test]
+    fn lower_bound() {
+        // one suit missing, won in 3
+        assert_eq!(Deck::parse(&ENDGAME.to_string()).lower_bound(), 1);
+        let text = "Play0: |3S |5H |AS |7H |4S JH
+        Play1: |AS |3S |TH |AH |9S TS
+        Play2: |AH |4H |5S |JS |3S 3H
+        Play3: |TH |4S |QH |TS |2H QS
+        Play4: |QH |3S |2S |AH 9H
+        Play5: |6S |8S |4H |7S 3H
+        Play6: |TH |QS |AS |5S 9H
+        Play7: |KS |KH |2S |6S TS
+        Play8: |JS |AH |8H |KS 7S
+        Play9: |7H |6S |3H |KS 4H
+        Deal0: 4S 9H 5H 7H 5H TH JH JH 6H JH
+        Deal1: JS 2S QH 6H 6H 7S JS QS 8H 4S
+        Deal2: 2H 5S 8S 3H 2H 8S KS 6H 9S KH
+        Deal3: |9S |2S |8H |KH |TS |AS |6S |9H |5H |7H
+        Deal4: |5S |2H |8H |7S |4H |9S |QH |8S |QS |KH
+        Off:";
+        // 44 neighbours, 3 of them with a king on top and |3S |2S
+        // in sequence: 40 breaks, 8 suits and 5 deals
+        assert_eq!(Deck::parse(&text.to_string()).lower_bound(), 53);
+    }
+
     #[test]
     fn talon_matches() {
         let text = "Play0: |TS |6S |5H |9H |8H JH..8H 9H
@@ -0,0 +1,195 @@
+use crate::deck::Deck;
+use std::fmt;
+
+// compared lexicographically, the position with the greater
+// score is kept in the beam first
+pub type Score = [i64; 4];
+
+pub trait Evaluator {
+    fn score(&self, deck: &Deck) -> Score;
+}
+
+// the hand tuned ordering the beam search always used
+pub struct DefaultEvaluator;
+
+impl Evaluator for DefaultEvaluator {
+    fn score(&self, deck: &Deck) -> Score {
+        let chaos = deck.chaos() as i64;
+        let ready = (deck.playable() + deck.in_off() + deck.free_plays()) as i64;
+        if chaos == 0 {
+            // once we are in straight win mode, we go differently:
+            // if the number of empty plays is equal, less in the off
+            // is actually a benefit (more strongly ordered)
+            return [0, ready, deck.free_plays() as i64, -(deck.in_off() as i64)];
+        }
+        [-chaos, ready, 0, 0]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Weights {
+    pub chaos: i64,
+    pub playable: i64,
+    pub under: i64,
+    pub hidden: i64,
+    pub order: i64,
+    pub talon_matches: i64,
+    pub free_plays: i64,
+    pub off: i64,
+}
+
+pub const WEIGHT_NAMES: [&str; 8] = [
+    "chaos",
+    "playable",
+    "under",
+    "hidden",
+    "order",
+    "talon_matches",
+    "free_plays",
+    "off",
+];
+
+impl Default for Weights {
+    // roughly what the default evaluator does
+    fn default() -> Self {
+        Weights {
+            chaos: -1000,
+            playable: 10,
+            under: 0,
+            hidden: 0,
+            order: 0,
+            talon_matches: 0,
+            free_plays: 10,
+            off: 130,
+        }
+    }
+}
+
+impl Weights {
+    pub fn get(&self, name: &str) -> Option<i64> {
+        match name {
+            "chaos" => Some(self.chaos),
+            "playable" => Some(self.playable),
+            "under" => Some(self.under),
+            "hidden" => Some(self.hidden),
+            "order" => Some(self.order),
+            "talon_matches" => Some(self.talon_matches),
+            "free_plays" => Some(self.free_plays),
+            "off" => Some(self.off),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: i64) -> bool {
+        let weight = match name {
+            "chaos" => &mut self.chaos,
+            "playable" => &mut self.playable,
+            "under" => &mut self.under,
+            "hidden" => &mut self.hidden,
+            "order" => &mut self.order,
+            "talon_matches" => &mut self.talon_matches,
+            "free_plays" => &mut self.free_plays,
+            "off" => &mut self.off,
+            _ => return false,
+        };
+        *weight = value;
+        true
+    }
+
+    // reads "name: value" lines, weights not listed stay at 0
+    pub fn parse(contents: &str) -> Result<Weights, String> {
+        let mut weights = Weights {
+            chaos: 0,
+            playable: 0,
+            under: 0,
+            hidden: 0,
+            order: 0,
+            talon_matches: 0,
+            free_plays: 0,
+            off: 0,
+        };
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut two = line.splitn(2, ':');
+            let name = two.next().unwrap_or("").trim();
+            let value = match two.next().map(|v| v.trim().parse::<i64>()) {
+                Some(Ok(value)) => value,
+                _ => return Err(format!("Invalid weight line '{}'", line)),
+            };
+            if !weights.set(name, value) {
+                return Err(format!("Unknown weight '{}'", name));
+            }
+        }
+        Ok(weights)
+    }
+}
+
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in WEIGHT_NAMES.iter() {
+            writeln!(f, "{}: {}", name, self.get(name).unwrap())?;
+        }
+        Ok(())
+    }
+}
+
+pub struct WeightedEvaluator {
+    weights: Weights,
+}
+
+impl WeightedEvaluator {
+    pub fn new(weights: Weights) -> WeightedEvaluator {
+        WeightedEvaluator { weights }
+    }
+}
+
+impl Evaluator for WeightedEvaluator {
+    fn score(&self, deck: &Deck) -> Score {
+        let w = &self.weights;
+        let score = w.chaos * deck.chaos() as i64
+            + w.playable * deck.playable() as i64
+            + w.under * deck.under() as i64
+            + w.hidden * deck.hidden() as i64
+            + w.order * deck.order() as i64
+            + w.talon_matches * deck.talon_matches() as i64
+            + w.free_plays * deck.free_plays() as i64
+            + w.off * deck.off_count() as i64;
+        [score, 0, 0, 0]
+    }
+}
+
+#[cfg(test)]
+mod evaluatortests {
+    use super::*;
+    use crate::deck::ENDGAME;
+
+    #[test]
+    fn parse_weights() {
+        let weights = Weights::parse("# tuned\nchaos: -5\n  off: 3\n").expect("parsed");
+        assert_eq!(weights.chaos, -5);
+        assert_eq!(weights.off, 3);
+        assert_eq!(weights.playable, 0);
+        assert_eq!(Weights::parse(&weights.to_string()), Ok(weights));
+        assert!(Weights::parse("chaos: many").is_err());
+        assert!(Weights::parse("luck: 1").is_err());
+    }
+
+    #[test]
+    fn weighted_prefers_less_chaos() {
+        let mut deck = Deck::parse(&ENDGAME.to_string());
+        let evaluator = WeightedEvaluator::new(Weights::default());
+        assert_eq!(
+            deck.shortest_path_with(
+                &evaluator,
+                10,
+                false,
+                None,
+                &mut crate::transposition::TranspositionTable::new()
+            ),
+            Some(3)
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use crate::deck::{Deck, Position};
+use crate::moves::Move;
+use crate::transposition::{PassThrough, TranspositionTable};
+use std::collections::HashMap;
+
+pub type MoveGenerator = fn(&Deck, &mut Vec<Move>);
+
+#[derive(Debug, PartialEq)]
+pub enum Optimal {
+    // the moves of a shortest solution
+    Solved(Vec<Move>),
+    // every sequence of generated moves was tried
+    Unsolvable,
+    // the node budget ran out before either was clear
+    GaveUp,
+}
+
+enum Step {
+    Found,
+    // the smallest estimate above the bound, None (or u32::MAX) if
+    // nothing is left
+    Exceeded(Option<u32>),
+    Aborted,
+}
+
+// iterative deepening A*: depth first searches bounded by the moves
+// done plus a lower bound of the moves to go, raising the bound to
+// the smallest estimate that exceeded it until a solution shows up.
+// As long as the estimate never overestimates, the first solution
+// found is a shortest one
+pub struct IdaStar {
+    generator: MoveGenerator,
+    max_nodes: usize,
+    nodes: usize,
+    // what failed searches proved about the moves a position needs at
+    // least, so the next iteration doesn't have to find out again
+    learned: HashMap<Position, u32, PassThrough>,
+}
+
+impl IdaStar {
+    pub fn new(generator: MoveGenerator, max_nodes: usize) -> IdaStar {
+        IdaStar {
+            generator,
+            max_nodes,
+            nodes: 0,
+            learned: HashMap::default(),
+        }
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    fn estimate(&self, deck: &Deck, position: &Position) -> u32 {
+        let learned = self.learned.get(position).copied().unwrap_or(0);
+        deck.lower_bound().max(learned)
+    }
+
+    pub fn solve(&mut self, deck: &Deck) -> Optimal {
+        let mut root = deck.clone();
+        root.reset_moves();
+        let mut bound = root.lower_bound();
+        loop {
+            let mut seen = TranspositionTable::new();
+            match self.search(&root, 0, bound, &mut seen) {
+                (Step::Found, Some(won)) => return Optimal::Solved(won.win_moves()),
+                (Step::Exceeded(Some(next)), _) if next < u32::MAX => bound = next,
+                (Step::Exceeded(_), _) => return Optimal::Unsolvable,
+                _ => return Optimal::GaveUp,
+            }
+        }
+    }
+
+    fn search(
+        &mut self,
+        deck: &Deck,
+        depth: u32,
+        bound: u32,
+        seen: &mut TranspositionTable,
+    ) -> (Step, Option<Deck>) {
+        let position = deck.position(deck.hash());
+        let estimate = self.estimate(deck, &position).saturating_add(depth);
+        if estimate > bound {
+            return (Step::Exceeded(Some(estimate)), None);
+        }
+        if deck.is_won() {
+            return (Step::Found, Some(deck.clone()));
+        }
+        if self.nodes >= self.max_nodes {
+            return (Step::Aborted, None);
+        }
+        self.nodes += 1;
+        let mut moves = vec![];
+        (self.generator)(deck, &mut moves);
+        let mut next: Option<u32> = None;
+        for m in &moves {
+            let newdeck = deck.apply_move(m);
+            let hash = newdeck.hash();
+            let estimate = if !seen.insert(&newdeck, hash, depth + 1) {
+                // reached before with at least as much of the bound left
+                // and it failed there (or it's a loop) - so it fails here
+                let position = newdeck.position(hash);
+                let estimate = self.estimate(&newdeck, &position);
+                estimate.saturating_add(depth + 1).max(bound + 1)
+            } else {
+                match self.search(&newdeck, depth + 1, bound, seen) {
+                    (Step::Exceeded(Some(estimate)), _) => estimate,
+                    (Step::Exceeded(None), _) => continue,
+                    found_or_aborted => return found_or_aborted,
+                }
+            };
+            if next.is_none_or(|n| estimate < n) {
+                next = Some(estimate);
+            }
+        }
+        // nothing within the bound, so this position needs more
+        let needed = next.map_or(u32::MAX, |n| n.saturating_sub(depth));
+        self.learned.insert(position, needed);
+        (Step::Exceeded(next), None)
+    }
+}
+
+#[cfg(test)]
+mod idatests {
+    use super::*;
+    use crate::deck::{ENDGAME, NINE_MOVES};
+
+    #[test]
+    fn endgame() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let mut ida = IdaStar::new(Deck::get_all_moves, 100_000);
+        assert_eq!(
+            ida.solve(&deck),
+            Optimal::Solved(vec![
+                Move::regular(1, 0, 0),
+                Move::regular(2, 0, 0),
+                Move::off(0, 0)
+            ])
+        );
+    }
+
+    #[test]
+    fn nine_moves() {
+        let deck = Deck::parse(&NINE_MOVES.to_string());
+        // too many moves to the empty piles for the complete
+        // generator, so the shortest of the pruned moves
+        let mut ida = IdaStar::new(Deck::get_moves, 100_000);
+        match ida.solve(&deck) {
+            Optimal::Solved(moves) => assert_eq!(moves.len(), 9),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn complete_endgame() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        // many more moves to the empty piles, none of them is shorter
+        let mut pruned = IdaStar::new(Deck::get_moves, 1000);
+        let mut complete = IdaStar::new(Deck::get_all_moves, 100_000);
+        assert_eq!(complete.solve(&deck), pruned.solve(&deck));
+        assert!(complete.nodes() > pruned.nodes());
+    }
+
+    #[test]
+    fn budget() {
+        let text = "Play0:
+        Play1: QH JH TH
+        Play2: 2H AH
+        Play3: KS
+        Play4: 5S 4S 3S 2S AS
+        Play5: |9S |9H 8H 7H 6H 5H
+        Play6: |7S |QS |KH |4H 3H
+        Play7: |8S JS TS
+        Play8: 6S
+        Play9:
+        Deal0:
+        Deal1:
+        Deal2:
+        Deal3:
+        Deal4:
+        Off: KS KH KH KS KH KS";
+        let deck = Deck::parse(&text.to_string());
+        let mut ida = IdaStar::new(Deck::get_moves, 10);
+        assert_eq!(ida.solve(&deck), Optimal::GaveUp);
+        assert_eq!(ida.nodes(), 10);
+    }
+}
@@ -1,19 +1,50 @@
 use std::fs;
+mod batch;
 mod card;
+mod checkpoint;
 mod deck;
+mod evaluator;
+mod ida;
+mod mcts;
 mod moves;
+mod nrpa;
 mod pile;
+mod progress;
+mod prover;
+mod recursion;
+mod regress;
+mod slip;
+mod slow;
+mod solution;
+mod solver;
+mod stats;
+mod transposition;
+mod tune;
+mod verify;
 use card::Card;
+use checkpoint::Checkpoint;
 use clap::{App, Arg};
 use deck::Deck;
 use deck::DeltaMove;
+use evaluator::{DefaultEvaluator, Evaluator, WeightedEvaluator, Weights};
+use ida::{IdaStar, MoveGenerator, Optimal};
+use moves::Move;
 use pile::Pile;
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use progress::{Cancel, Observer, Progress};
+use prover::{Prover, Verdict};
+use slip::{ChildStatus, SlipReport};
+use solution::{Format, Solution};
+use solver::{Budget, Slow, Solver, Strategy};
+use stats::StatsOutput;
 use std::collections::HashSet;
 use std::fs::File;
 use std::io;
 use std::io::Write;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use transposition::SeenLimit;
+use verify::Replay;
 
 fn generate_deck(filename: &str) {
     let mut deck = Deck::empty();
@@ -55,48 +86,193 @@ fn generate_deck(filename: &str) {
     }
 }
 
-fn play_one_round(
-    filename: &str,
+// how to run the searches
+struct Settings {
     cap: usize,
     suits: usize,
-    orig_filename: Option<&str>,
     debug: bool,
-    yaml: bool,
+    // structured output of the solution instead of the explanations
+    format: Option<Format>,
+    output: Option<String>,
+    seen_limit: Option<SeenLimit>,
+    evaluator: Box<dyn Evaluator>,
+    // given or taken from the clock, it is always written out
+    seed: u64,
+    // moves in notation to play before searching
+    play: Option<String>,
+    stats: Option<StatsOutput>,
+    // print the progress to stderr
+    progress: bool,
+    cancel: Cancel,
+    strategy: Strategy,
+    // of the solvers that can stop in between
+    seconds: Option<f64>,
+    iterations: Option<usize>,
+    // of the slow solver
+    checkpoint: Option<String>,
+    checkpoint_every: Duration,
+}
+
+impl Settings {
+    fn shuffle_unknowns(&self, deck: &mut Deck) {
+        deck.shuffle_unknowns_with_seed(self.suits, self.seed);
+    }
+
+    fn write_solution(&self, solution: &Solution, format: Format) {
+        let text = solution.to_text(format);
+        match &self.output {
+            Some(output) => {
+                let mut file = match File::create(output) {
+                    Err(why) => panic!("couldn't create {}: {}", output, why),
+                    Ok(file) => file,
+                };
+                if let Err(why) = file.write_all(text.as_bytes()) {
+                    panic!("couldn't write to {} {}", output, why);
+                }
+            }
+            None => print!("{}", text),
+        }
+    }
+
+    fn budget(&self) -> Budget {
+        Budget {
+            cap: self.cap,
+            seen_limit: self.seen_limit,
+            seconds: self.seconds,
+            iterations: self.iterations,
+        }
+    }
+
+    // the picks are printed unless the solution is
+    fn slow(&self, filename: &str, resume: Option<Checkpoint>) -> Slow<'_> {
+        Slow {
+            game: filename.to_string(),
+            suits: self.suits,
+            verbose: self.format.is_none(),
+            checkpoint: self.checkpoint.clone(),
+            checkpoint_every: self.checkpoint_every,
+            resume,
+            ..Slow::new(self.evaluator.as_ref(), self.seed)
+        }
+    }
+
+    // follows a search with the settings' cancel token
+    fn observer(&self) -> Observer<'static> {
+        let mut observer = Observer::new(self.cancel.clone());
+        if self.progress {
+            observer.progress = Some(Box::new(|progress: &Progress| {
+                eprint!("depth {} frontier {}", progress.depth, progress.frontier);
+                match progress.best {
+                    Some(best) => eprintln!(" best {}", best),
+                    None => eprintln!(),
+                }
+            }));
+        }
+        observer
+    }
+
+    fn play_moves(&self, deck: &mut Deck) {
+        if let Some(play) = &self.play {
+            for m in Move::parse_list(play, deck).unwrap_or_else(|e| panic!("{}", e)) {
+                *deck = deck.apply_move(&m);
+                deck.reset_moves();
+            }
+        }
+    }
+}
+
+// the first interrupt stops the search between depths, the next one
+// doesn't wait for that
+fn cancel_on_interrupt(cancel: &Cancel) {
+    let cancel = cancel.clone();
+    ctrlc::set_handler(move || {
+        if cancel.is_cancelled() {
+            std::process::exit(130);
+        }
+        cancel.cancel();
+    })
+    .expect("Error setting the interrupt handler");
+}
+
+fn play_one_round(
+    filename: &str,
+    orig_filename: Option<&str>,
+    settings: &Settings,
+    resume: Option<Checkpoint>,
 ) -> bool {
+    let debug = settings.debug;
+    let yaml = settings.format.is_some();
     let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
     let mut deck = Deck::parse(&contents);
-    deck.shuffle_unknowns(suits);
+    settings.shuffle_unknowns(&mut deck);
+    settings.play_moves(&mut deck);
 
-    let result = deck.shortest_path(cap, debug, None);
-    if result.is_none() {
-        println!("No win");
+    let mut solution = Solution::new(filename, &deck);
+    let start = Instant::now();
+    let mut observer = settings.observer();
+    let mut solver: Box<dyn Solver> = match settings.strategy {
+        Strategy::Slow => Box::new(settings.slow(filename, resume)),
+        strategy => strategy.solver(settings.evaluator.as_ref(), debug, settings.seed),
+    };
+    let outcome = solver.solve(&deck, &settings.budget(), &mut observer);
+    let stats = observer.stats;
+    if let Some(output) = settings.stats {
+        // keep a structured solution on stdout readable
+        if yaml {
+            eprint!("{}", stats.to_text(output));
+        } else {
+            print!("{}", stats.to_text(output));
+        }
+    }
+    solution.suits = settings.suits;
+    solution.seed = Some(settings.seed);
+    solution.cap = settings.cap;
+    solution.nodes = stats.expanded;
+    solution.seconds = start.elapsed().as_secs_f64();
+    solution.length = outcome.moves.as_ref().map(|moves| moves.len());
+    if settings.seen_limit.is_some() && !yaml {
+        println!(
+            "Dropped {} seen positions, {} of them expanded again",
+            outcome.dropped, outcome.reexpanded
+        );
+    }
+    if stats.cancelled && outcome.moves.is_none() {
+        if outcome.line.is_empty() {
+            eprintln!("Cancelled before a solution was found");
+        } else {
+            let moves: Vec<String> = outcome.line.iter().map(|m| m.notation()).collect();
+            eprintln!(
+                "Cancelled after depth {}, the most promising line: {}",
+                stats.depths.len(),
+                moves.join(", ")
+            );
+        }
         return false;
     }
+    let win_moves = match outcome.moves {
+        Some(moves) => moves,
+        None => {
+            match settings.format {
+                Some(format) => settings.write_solution(&solution, format),
+                None => println!("No win"),
+            }
+            return false;
+        }
+    };
     let mut won_decks: HashSet<u64> = HashSet::new();
     let mut mc = 0;
     let mut orig = deck.clone();
     orig.reset_moves();
-    if yaml {
-        println!("moves:");
-    }
-    for m in deck.win_moves() {
+    let lower_bound = orig.lower_bound();
+    solution.set_moves(&win_moves);
+    let moves = win_moves.len();
+    for m in win_moves {
         won_decks.insert(orig.hash());
         if !m.is_off() {
             mc += 1;
         }
-        if yaml {
-            println!("  - from: {}", m.from());
-            println!("    to: {}", m.to());
-            println!("    index: {}", m.index());
-            if m.is_off() {
-                println!("    off: true");
-            }
-            if m.is_talon() {
-                println!("    talon: true");
-            }
-            println!("    number: {}", mc);
-        } else {
-            print!("Move {}: {} ", mc, orig.explain_move(&m));
+        if !yaml {
+            print!("Move {}: {} [{}] ", mc, orig.explain_move(&m), m.notation());
             deck = deck.apply_move(&m);
         }
         let dm = DeltaMove::new(&orig, &m);
@@ -114,11 +290,11 @@ fn play_one_round(
             );
         }
         if !dm.any_good() {
-            println!("Not a good move:\n{} {:?}", orig.to_string(), dm);
+            eprintln!("Not a good move:\n{} {:?}", orig.to_string(), dm);
         }
         orig = newdeck;
         if orig.top_card_unknown(m.from()) {
-            println!("What's up?");
+            eprintln!("What's up?");
             let stdin = io::stdin();
             let buffer = &mut String::new();
 
@@ -134,7 +310,7 @@ fn play_one_round(
 
             match file.write_all(orig.to_string().as_bytes()) {
                 Err(why) => panic!("couldn't write to tmp {}", why),
-                Ok(_) => println!("successfully wrote to tmp"),
+                Ok(_) => eprintln!("successfully wrote to tmp"),
             }
 
             if orig_filename.is_some() {
@@ -150,13 +326,23 @@ fn play_one_round(
 
                 match file.write_all(deck2.to_string().as_bytes()) {
                     Err(why) => panic!("couldn't write to {} {}", filename, why),
-                    Ok(_) => println!("successfully wrote to {}", filename),
+                    Ok(_) => eprintln!("successfully wrote to {}", filename),
                 }
             }
 
             return true;
         }
     }
+    if let Some(format) = settings.format {
+        settings.write_solution(&solution, format);
+    } else {
+        println!(
+            "Solution of {} moves, at least {} needed (gap {})",
+            moves,
+            lower_bound,
+            moves as u32 - lower_bound
+        );
+    }
     if debug {
         deck.reset_moves();
        // deck.shortest_path(cap, debug, Some(won_decks));
@@ -164,124 +350,150 @@ fn play_one_round(
     false
 }
 
-struct WeightedDeck {
-    deck: Deck,
-    depth: u32,
-    moves: u32,
-    total: u32,
-    hash: u64,
-}
-
-impl PartialOrd for WeightedDeck {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+fn play_rerun_slip<E: Evaluator + ?Sized>(
+    filename: &str,
+    evaluator: &E,
+    seen_limit: Option<SeenLimit>,
+) -> bool {
+    let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
+    let report = SlipReport::parse(&contents).unwrap_or_else(|e| panic!("{}", e));
+    let rerun = slip::rerun(&report, evaluator, seen_limit).unwrap_or_else(|e| panic!("{}", e));
+    let show = |estimate: Option<u32>| estimate.map_or("-".to_string(), |e| e.to_string());
+    println!(
+        "Position at depth {} (cap {}): estimated {}, now {}",
+        report.depth,
+        report.cap,
+        report.estimate,
+        show(rerun.estimate)
+    );
+    println!(
+        "{:<10} {:<8} {:>8} {:>4}",
+        "move", "status", "recorded", "now"
+    );
+    for (child, now) in report.children.iter().zip(rerun.children.iter()) {
+        let status = match child.status {
+            ChildStatus::Searched => "searched",
+            ChildStatus::Seen => "seen",
+            ChildStatus::Pruned => "pruned",
+        };
+        println!(
+            "{:<10} {:<8} {:>8} {:>4}",
+            child.m.notation(),
+            status,
+            show(child.estimate),
+            show(*now)
+        );
     }
-}
-
-impl Ord for WeightedDeck {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other
-            .total
-            .cmp(&self.total)
-            .then(self.depth.cmp(&other.depth))
+    if rerun.reproduces() {
+        println!(
+            "The slip reproduces: the best move needs {}, the position {}",
+            show(slip::best_child(&rerun.children)),
+            show(rerun.estimate)
+        );
+    } else {
+        println!("The slip does not reproduce");
     }
+    rerun.reproduces()
 }
 
-impl PartialEq for WeightedDeck {
-    fn eq(&self, other: &Self) -> bool {
-        self.hash == other.hash
+fn play_optimal(deck: &Deck, generator: MoveGenerator, max_nodes: usize) {
+    if !deck.is_fully_known() {
+        println!("Unknown cards are guessed, the solution is only optimal for this guess");
     }
-}
-
-impl Eq for WeightedDeck {}
-
-fn pick(
-    heap: &mut BinaryHeap<WeightedDeck>,
-    seen: &mut HashSet<u64>,
-    cap: usize,
-    best_before: usize,
-    orig: &Deck,
-) -> usize {
-    let wdeck = heap.pop();
-    if wdeck.is_none() {
-        return 0;
-    }
-    let wdeck = wdeck.unwrap();
-    let depth = wdeck.depth;
-    print!("Picked {}+{}={} (", depth, wdeck.moves, wdeck.total);
-
-    let deck = wdeck.deck;
-    if deck.is_won() {
-        println!("WON");
-        return 0;
-    }
-    let mut moves = vec![];
-    deck.get_moves(&mut moves);
-    let mut best_total = deck::MAX_MOVES;
-    let mut bestdeck: Option<Deck> = None;
-
-    for m in &moves {
-        //deck.explain_move(&m);
-        let mut newdeck = deck.apply_move(m);
-        let hash = newdeck.hash();
-        if seen.contains(&hash) {
-            continue;
+    let mut ida = IdaStar::new(generator, max_nodes);
+    match ida.solve(deck) {
+        Optimal::Solved(moves) => {
+            let lower_bound = deck.lower_bound();
+            let mut deck = deck.clone();
+            let mut mc = 0;
+            // numbered and counted like the beam search's solutions
+            for m in &moves {
+                if !m.is_off() {
+                    mc += 1;
+                }
+                println!("Move {}: {}", mc, deck.explain_move(m));
+                deck = deck.apply_move(m);
+            }
+            println!(
+                "Shortest solution of {} moves, at least {} needed (gap {}, {} nodes)",
+                moves.len(),
+                lower_bound,
+                moves.len() as u32 - lower_bound,
+                ida.nodes()
+            );
         }
-        seen.insert(hash);
-        //println!("New\n{}", newdeck.to_string());
-        let orig_move_index = newdeck.get_moves_index();
-        let won = newdeck.shortest_path(cap, false, None);
+        Optimal::Unsolvable => println!("No win ({} nodes)", ida.nodes()),
+        Optimal::GaveUp => println!("Gave up after {} nodes", ida.nodes()),
+    }
+}
 
-        if won.is_none() || won.unwrap() < 0 {
-            //println!("Move didn't win");
-        } else {
-            let won = won.unwrap() as u32;
-            //println!("Move gave {}", won);
-            print!("{} ", depth + won + 1);
-            if ((won + depth + 1) as usize) < best_total {
-                best_total = (won + depth + 1) as usize;
-                if best_total < best_before {
-                    bestdeck = Some(newdeck.clone());
+fn play_prove(deck: &Deck, generator: MoveGenerator, max_nodes: usize, debug: bool) {
+    if !deck.is_fully_known() {
+        println!("Unknown cards are guessed, the verdict is only for this guess");
+    }
+    let mut prover = Prover::new(generator, max_nodes);
+    match prover.prove(deck) {
+        Verdict::Won(moves) => {
+            // the first win found depth first, anything but short
+            if debug {
+                let mut deck = deck.clone();
+                let mut mc = 0;
+                for m in &moves {
+                    if !m.is_off() {
+                        mc += 1;
+                    }
+                    println!("Move {}: {}", mc, deck.explain_move(m));
+                    deck = deck.apply_move(m);
+                    deck.reset_moves();
                 }
             }
-            newdeck.set_moves_index(orig_move_index);
-            heap.push(WeightedDeck {
-                deck: newdeck,
-                hash: hash,
-                depth: depth + 1,
-                moves: won as u32,
-                total: won + depth + 1,
-            });
+            println!("Won in {} moves ({} nodes)", moves.len(), prover.nodes());
         }
+        Verdict::Lost => println!("Lost, no win possible ({} nodes)", prover.nodes()),
+        Verdict::Unknown => println!("Unknown, gave up after {} nodes", prover.nodes()),
     }
-    println!(")");
-    if best_total > wdeck.total as usize {
-        println!("There was a slip!");
-        let filename = format!("slip.{}-{}", best_total, wdeck.total);
-        let mut file = match File::create(&filename) {
-            Err(why) => panic!("couldn't create tmp: {}", why),
-            Ok(file) => file,
-        };
+}
 
-        match file.write_all(deck.to_string().as_bytes()) {
-            Err(why) => panic!("couldn't write to {} {}", filename, why),
-            Ok(_) => println!("successfully wrote to {}", filename),
+fn play_verify(deck: &Deck, solution: &str) -> bool {
+    let contents = fs::read_to_string(solution).expect("Something went wrong reading the solution");
+    let (moves, deal) = verify::parse_solution(&contents).unwrap_or_else(|e| panic!("{}", e));
+    // the unknown cards may have been shuffled differently this time
+    let deck = deal.as_ref().unwrap_or(deck);
+    match verify::replay(deck, &moves) {
+        Replay::Won(count) => {
+            println!("Won after {} moves", count);
+            true
+        }
+        Replay::NotWon(count) => {
+            println!("All {} moves are legal, but the game isn't won", count);
+            false
+        }
+        Replay::Illegal(number, m, why) => {
+            println!("Move {} ({}) is illegal: {}", number, m.notation(), why);
+            false
         }
     }
-    if let Some(deck) = bestdeck {
-        let moves = deck.win_moves();
-        let mut deck = orig.clone();
-        let mut mc = 0;
-        for m in moves {
-            if !m.is_off() {
-                mc += 1;
-            }
-            print!("Move {}: {}", mc, deck.explain_move(&m));
-            deck = deck.apply_move(&m);
-            println!(" (Chaos {} Playable {})", deck.chaos(), deck.playable());
+}
+
+fn play_seek(deck: &Deck, solution: &str, number: usize, output: Option<&str>) {
+    let contents = if Path::new(solution).is_file() {
+        fs::read_to_string(solution).expect("Something went wrong reading the solution")
+    } else {
+        solution.to_string()
+    };
+    let (moves, deal) = verify::load_moves(&contents, deck).unwrap_or_else(|e| panic!("{}", e));
+    let deck = deal.as_ref().unwrap_or(deck);
+    let deck = verify::seek(deck, &moves, number).unwrap_or_else(|e| panic!("{}", e));
+    println!("{}", deck.to_string());
+    if let Some(output) = output {
+        let mut file = match File::create(output) {
+            Err(why) => panic!("couldn't create {}: {}", output, why),
+            Ok(file) => file,
+        };
+        if let Err(why) = file.write_all(deck.to_string().as_bytes()) {
+            panic!("couldn't write to {} {}", output, why);
         }
     }
-    best_total
 }
 
 fn main() {
@@ -296,7 +508,7 @@ fn main() {
         .arg(
             Arg::with_name("filename")
                 .takes_value(true)
-                .multiple(false)
+                .multiple(true)
                 .required(true)
                 .help("Temporary file name"),
         )
@@ -305,6 +517,19 @@ fn main() {
                 .long("yaml")
                 .help("Output moves as yaml"),
         )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["yaml", "json"])
+                .help("Output the solution with its settings as yaml or json"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .takes_value(true)
+                .help("Write the yaml or json solution to this file"),
+        )
         .arg(
             Arg::with_name("cap")
                 .long("cap")
@@ -328,10 +553,196 @@ fn main() {
                 .long("generate")
                 .help("Generate a new deck file"),
         )
+        .arg(
+            Arg::with_name("seen-limit")
+                .long("seen-limit")
+                .takes_value(true)
+                .help("Maximum number of positions to remember per beam search, the picks of --slow are all kept"),
+        )
+        .arg(
+            Arg::with_name("seen-keep")
+                .long("seen-keep")
+                .takes_value(true)
+                .default_value("2")
+                .help("Depths to remember once the seen limit is hit"),
+        )
+        .arg(
+            Arg::with_name("weights")
+                .long("weights")
+                .takes_value(true)
+                .help("Order the beam by the weights in this file"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .help("Seed to shuffle the unknown cards with"),
+        )
+        .arg(
+            Arg::with_name("tune")
+                .long("tune")
+                .takes_value(true)
+                .help("Tune the weights over the games in the directory, write them here"),
+        )
+        .arg(
+            Arg::with_name("regress")
+                .long("regress")
+                .takes_value(true)
+                .help("Solve the games in the directory again and compare with these results"),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .takes_value(true)
+                .possible_values(&["report", "json"])
+                .help("Print what the search did as a report or json"),
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .help("Print the depth, frontier and best solution while searching"),
+        )
+        .arg(
+            Arg::with_name("batch")
+                .long("batch")
+                .takes_value(true)
+                .help("Solve the game files and directories, write the solutions here"),
+        )
+        .arg(
+            Arg::with_name("compare")
+                .long("compare")
+                .requires("batch")
+                .help("Run the beam search as well and show its lengths next to the --solver"),
+        )
+        .arg(
+            Arg::with_name("threads")
+                .long("threads")
+                .takes_value(true)
+                .help("Number of games the batch solves at once"),
+        )
+        .arg(
+            Arg::with_name("optimal")
+                .long("optimal")
+                .help("Search a provably shortest solution with IDA*"),
+        )
+        .arg(
+            Arg::with_name("prove")
+                .long("prove")
+                .help("Search all moves to prove the deal won or lost"),
+        )
+        .arg(
+            Arg::with_name("play")
+                .long("play")
+                .takes_value(true)
+                .help("Play these moves (\"3>8@5, deal, off 4\") or a file of them first"),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .takes_value(true)
+                .help("Replay the moves of this yaml solution and check they win"),
+        )
+        .arg(
+            Arg::with_name("seek")
+                .long("seek")
+                .takes_value(true)
+                .requires("solution")
+                .help("Print the deal after this move of the solution"),
+        )
+        .arg(
+            Arg::with_name("solution")
+                .long("solution")
+                .takes_value(true)
+                .help("The yaml solution or the moves in notation to seek in"),
+        )
+        .arg(
+            Arg::with_name("write")
+                .long("write")
+                .takes_value(true)
+                .help("Write the deal found with --seek to this game file"),
+        )
+        .arg(
+            Arg::with_name("explain")
+                .long("explain")
+                .help("List every move of the deal and why the solver prunes it"),
+        )
+        .arg(
+            Arg::with_name("generator")
+                .long("generator")
+                .takes_value(true)
+                .possible_values(&["complete", "pruned"])
+                .default_value("complete")
+                .help("The moves the optimal search and the proof try"),
+        )
+        .arg(
+            Arg::with_name("max-nodes")
+                .long("max-nodes")
+                .takes_value(true)
+                .default_value("10000000")
+                .help("Give up the optimal search or the proof after this many nodes"),
+        )
         .arg(
             Arg::with_name("slow")
                 .long("slow")
-                .help("Use A+ to search further"),
+                .help("Use A+ to search further, the same as --solver slow"),
+        )
+        .arg(
+            Arg::with_name("slack")
+                .long("slack")
+                .takes_value(true)
+                .default_value("5")
+                .help("Moves a line of the recursion solver may need more than the best one"),
+        )
+        .arg(
+            Arg::with_name("trust")
+                .long("trust")
+                .takes_value(true)
+                .default_value("20")
+                .help("Take the estimate as it is when less moves are left"),
+        )
+        .arg(
+            Arg::with_name("solver")
+                .long("solver")
+                .takes_value(true)
+                .possible_values(&["beam", "slow", "recursion", "mcts", "nrpa"])
+                .default_value("beam")
+                .help("The search of the default mode, --batch and --regress"),
+        )
+        .arg(
+            Arg::with_name("seconds")
+                .long("seconds")
+                .takes_value(true)
+                .help("Stop every solver but the beam search after this many seconds"),
+        )
+        .arg(
+            Arg::with_name("iterations")
+                .long("iterations")
+                .takes_value(true)
+                .help("Iterations of the mcts solver, playouts of nrpa (200 without --seconds)"),
+        )
+        .arg(
+            Arg::with_name("rerun-slip")
+                .long("rerun-slip")
+                .help("Search the position and moves of the slip report given as file again"),
+        )
+        .arg(
+            Arg::with_name("checkpoint")
+                .long("checkpoint")
+                .takes_value(true)
+                .help("Save the slow search to this file now and then and when interrupted"),
+        )
+        .arg(
+            Arg::with_name("checkpoint-every")
+                .long("checkpoint-every")
+                .takes_value(true)
+                .default_value("300")
+                .help("Seconds between two checkpoints"),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .takes_value(true)
+                .help("Continue the slow search saved in this checkpoint"),
         )
         .get_matches();
 
@@ -346,45 +757,248 @@ fn main() {
     }
 
     let suits = matches.value_of("suits").unwrap().parse().unwrap();
+    let seen_limit = matches.value_of("seen-limit").map(|limit| SeenLimit {
+        max_entries: limit.parse().expect("Integer"),
+        keep_depths: matches
+            .value_of("seen-keep")
+            .unwrap()
+            .parse()
+            .expect("Integer"),
+    });
+
+    let weights = matches.value_of("weights").map(|weights| {
+        let contents =
+            fs::read_to_string(weights).expect("Something went wrong reading the weights");
+        Weights::parse(&contents).unwrap_or_else(|e| panic!("{}", e))
+    });
+    let seed = matches
+        .value_of("seed")
+        .map(|seed| seed.parse().expect("Integer"));
+
+    let strategy = Strategy::parse(
+        if matches.is_present("slow") {
+            "slow"
+        } else {
+            matches.value_of("solver").unwrap()
+        },
+        matches.value_of("slack").unwrap().parse().expect("Integer"),
+        matches.value_of("trust").unwrap().parse().expect("Integer"),
+    )
+    .expect("known solver");
+    let seconds = matches
+        .value_of("seconds")
+        .map(|seconds| seconds.parse().expect("Number"));
+    let budget = Budget {
+        cap,
+        seen_limit,
+        seconds,
+        iterations: match matches.value_of("iterations") {
+            Some(iterations) => Some(iterations.parse().expect("Integer")),
+            None if seconds.is_none() => Some(200),
+            None => None,
+        },
+    };
+    if (matches.is_present("checkpoint") || matches.is_present("resume"))
+        && strategy != Strategy::Slow
+    {
+        clap::Error::with_description(
+            "--checkpoint and --resume are for the slow solver",
+            clap::ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
+    if matches.is_present("compare") && strategy.samples() && seconds.is_none() {
+        clap::Error::with_description(
+            "--compare needs --seconds to give the mcts and nrpa solvers a budget like the beam's",
+            clap::ErrorKind::MissingRequiredArgument,
+        )
+        .exit();
+    }
+
+    if let Some(output) = matches.value_of("tune") {
+        let games = tune::collect_games(Path::new(filename), suits);
+        let best = tune::tune(
+            &games,
+            weights.unwrap_or_default(),
+            cap,
+            seed.unwrap_or(0),
+            output,
+        );
+        println!("Best weights:\n{}", best);
+        return;
+    }
+
+    if matches.is_present("rerun-slip") {
+        let evaluator: Box<dyn Evaluator> = match weights {
+            Some(weights) => Box::new(WeightedEvaluator::new(weights)),
+            None => Box::new(DefaultEvaluator),
+        };
+        play_rerun_slip(filename, evaluator.as_ref(), seen_limit);
+        return;
+    }
+
+    if let Some(results) = matches.value_of("regress") {
+        let evaluator: Box<dyn Evaluator> = match weights {
+            Some(weights) => Box::new(WeightedEvaluator::new(weights)),
+            None => Box::new(DefaultEvaluator),
+        };
+        let regressions = regress::regress(
+            &regress::collect_results(Path::new(results)),
+            Path::new(filename),
+            suits,
+            seed.unwrap_or(0),
+            matches
+                .value_of("cap")
+                .map(|cap| cap.parse().expect("Integer")),
+            strategy
+                .solver(evaluator.as_ref(), false, seed.unwrap_or(0))
+                .as_mut(),
+            &budget,
+        );
+        print!("{}", regress::summary(&regressions));
+        return;
+    }
+
+    if let Some(output) = matches.value_of("batch") {
+        let filenames: Vec<&str> = matches.values_of("filename").unwrap().collect();
+        let settings = batch::BatchSettings {
+            strategy,
+            budget,
+            seed: seed.unwrap_or_else(Deck::time_seed),
+            weights,
+            format: matches
+                .value_of("format")
+                .and_then(Format::parse)
+                .unwrap_or(Format::Yaml),
+        };
+        let threads = matches.value_of("threads").map_or_else(
+            || thread::available_parallelism().map_or(1, |n| n.get()),
+            |threads| threads.parse().expect("Integer"),
+        );
+        let games = batch::collect(&filenames, suits);
+        let start = Instant::now();
+        if matches.is_present("compare") {
+            // the beam search first, each into a directory of its own
+            let beam = batch::BatchSettings {
+                strategy: Strategy::Beam,
+                ..settings
+            };
+            let output = Path::new(output);
+            let beam = batch::run(&games, &beam, &output.join("beam"), threads);
+            let other = batch::run(&games, &settings, &output.join(strategy.name()), threads);
+            print!("{}", batch::compare(strategy.name(), &beam, &other));
+        } else {
+            let results = batch::run(&games, &settings, Path::new(output), threads);
+            print!("{}", batch::report(&results));
+        }
+        println!(
+            "{:.2} seconds with {} threads",
+            start.elapsed().as_secs_f64(),
+            threads
+        );
+        return;
+    }
+
+    let evaluator: Box<dyn Evaluator> = match weights {
+        Some(weights) => Box::new(WeightedEvaluator::new(weights)),
+        None => Box::new(DefaultEvaluator),
+    };
+    // the deal is shuffled the same way as the one of the checkpoint
+    let resume = matches
+        .value_of("resume")
+        .map(|resume| Checkpoint::load(resume).unwrap_or_else(|e| panic!("{}", e)));
+    let settings = Settings {
+        cap,
+        suits: resume.as_ref().map_or(suits, |checkpoint| checkpoint.suits),
+        debug: matches.is_present("debug"),
+        format: match matches.value_of("format") {
+            Some(format) => Format::parse(format),
+            None if matches.is_present("yaml") => Some(Format::Yaml),
+            None => None,
+        },
+        output: matches.value_of("output").map(|output| output.to_string()),
+        seen_limit,
+        evaluator,
+        seed: resume
+            .as_ref()
+            .and_then(|checkpoint| checkpoint.seed)
+            .or(seed)
+            .unwrap_or_else(Deck::time_seed),
+        play: matches.value_of("play").map(|play| {
+            if Path::new(play).is_file() {
+                fs::read_to_string(play).expect("Something went wrong reading the moves")
+            } else {
+                play.to_string()
+            }
+        }),
+        stats: matches.value_of("stats").and_then(StatsOutput::parse),
+        progress: matches.is_present("progress"),
+        cancel: Cancel::new(),
+        strategy,
+        seconds: budget.seconds,
+        iterations: budget.iterations,
+        checkpoint: matches
+            .value_of("checkpoint")
+            .or(matches.value_of("resume"))
+            .map(|checkpoint| checkpoint.to_string()),
+        checkpoint_every: Duration::from_secs(
+            matches
+                .value_of("checkpoint-every")
+                .unwrap()
+                .parse()
+                .expect("Integer"),
+        ),
+    };
 
     let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
     let mut deck = Deck::parse(&contents);
-    deck.shuffle_unknowns(suits);
+    settings.shuffle_unknowns(&mut deck);
+    settings.play_moves(&mut deck);
 
-    if matches.is_present("slow") {
-        let mut heap: BinaryHeap<WeightedDeck> = BinaryHeap::new();
-        let mc = deck.shortest_path(cap, false, None).unwrap();
-        deck.reset_moves();
-        assert!(mc > 0);
-        heap.push(WeightedDeck {
-            hash: deck.hash(),
-            deck: deck.clone(),
-            depth: 0,
-            moves: mc as u32,
-            total: mc as u32,
-        });
-        let mut seen = HashSet::new();
-        let mut best = deck::MAX_MOVES;
+    if let Some(solution) = matches.value_of("verify") {
+        if !play_verify(&deck, solution) {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-        loop {
-            let current_best = pick(&mut heap, &mut seen, cap, best, &deck);
-            if current_best == 0 {
-                break;
-            }
-            if best > current_best {
-                best = current_best;
-            }
+    if let Some(number) = matches.value_of("seek") {
+        play_seek(
+            &deck,
+            matches.value_of("solution").unwrap(),
+            number.parse().expect("Integer"),
+            matches.value_of("write"),
+        );
+        return;
+    }
+
+    if matches.is_present("explain") {
+        for (m, why) in deck.explain_pruning() {
+            println!("{}: {}", deck.explain_move(&m), why);
         }
+        return;
+    }
+
+    let max_nodes = matches
+        .value_of("max-nodes")
+        .unwrap()
+        .parse()
+        .expect("Integer");
+    // the beam's pruning would make proofs and shortest solutions
+    // only hold for the moves it leaves
+    let generator: MoveGenerator = match matches.value_of("generator").unwrap() {
+        "pruned" => Deck::get_moves,
+        _ => Deck::get_all_moves,
+    };
+    if matches.is_present("optimal") {
+        play_optimal(&deck, generator, max_nodes);
+    } else if matches.is_present("prove") {
+        play_prove(&deck, generator, max_nodes, settings.debug);
     } else {
+        cancel_on_interrupt(&settings.cancel);
+        let mut resume = resume;
         loop {
-            if !play_one_round(
-                filename,
-                cap,
-                suits,
-                matches.value_of("orig"),
-                matches.is_present("debug"),
-                matches.is_present("yaml"),
-            ) {
+            if !play_one_round(filename, matches.value_of("orig"), &settings, resume.take()) {
                 break;
             }
         }
@@ -0,0 +1,317 @@
+use crate::deck::{Deck, Position, MAX_MOVES};
+use crate::moves::Move;
+use crate::progress::{Observer, Progress};
+use crate::transposition::PassThrough;
+use std::collections::HashSet;
+use std::time::Instant;
+
+// xorshift, the rollouts only need to differ from each other
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    pub fn chance(&mut self, probability: f64) -> bool {
+        (self.next() % 1_000_000) as f64 / 1_000_000. < probability
+    }
+}
+
+struct Node {
+    m: Option<Move>,
+    parent: usize,
+    children: Vec<usize>,
+    // none until the node is reached the first time
+    untried: Option<Vec<Move>>,
+    visits: u32,
+    reward: f64,
+}
+
+// the rollouts try a random move now and then
+const EPSILON: f64 = 0.1;
+const EXPLORATION: f64 = 0.7;
+
+// a win is worth at least 0.5, the shorter the more. Else what made
+// it into the off and what is playable count
+fn reward(deck: &Deck, length: usize) -> f64 {
+    if deck.is_won() {
+        return 1. - 0.5 * length as f64 / MAX_MOVES as f64;
+    }
+    0.5 * (deck.in_off() + deck.playable()).min(208) as f64 / 208.
+}
+
+// the greedy pick of the rollouts: least chaos, then the most cards
+// ready to play
+fn rollout_score(deck: &Deck) -> (i64, i64) {
+    (
+        -(deck.chaos() as i64),
+        (deck.playable() + deck.in_off() + deck.free_plays()) as i64,
+    )
+}
+
+// monte carlo tree search with UCT. The tree is grown by one move
+// per iteration and the rest of the game is played out greedily
+pub struct Mcts {
+    nodes: Vec<Node>,
+    rng: Rng,
+    pub iterations: usize,
+    // moves played in rollouts
+    pub rollout_moves: usize,
+    pub best: Option<Vec<Move>>,
+}
+
+impl Mcts {
+    pub fn new(seed: u64) -> Mcts {
+        Mcts {
+            nodes: vec![],
+            rng: Rng::new(seed),
+            iterations: 0,
+            rollout_moves: 0,
+            best: None,
+        }
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // runs iterations until there are none left or the deadline
+    // passed, the best win found
+    pub fn search(
+        &mut self,
+        deck: &Deck,
+        iterations: Option<usize>,
+        deadline: Option<Instant>,
+        observer: &mut Observer,
+    ) -> Option<Vec<Move>> {
+        let mut root = deck.clone();
+        root.reset_moves();
+        self.nodes = vec![Node {
+            m: None,
+            parent: 0,
+            children: vec![],
+            untried: None,
+            visits: 0,
+            reward: 0.,
+        }];
+        loop {
+            if observer.is_cancelled()
+                || iterations.is_some_and(|n| self.iterations >= n)
+                || deadline.is_some_and(|d| Instant::now() >= d)
+            {
+                break;
+            }
+            let depth = self.iterate(&root);
+            self.iterations += 1;
+            if self.iterations.is_multiple_of(100) {
+                observer.report(Progress {
+                    depth,
+                    frontier: self.nodes.len(),
+                    best: self.best.as_ref().map(|moves| moves.len()),
+                });
+            }
+        }
+        self.best.clone()
+    }
+
+    fn uct(&self, parent: usize, child: usize) -> f64 {
+        let child = &self.nodes[child];
+        let parent = &self.nodes[parent];
+        if child.visits == 0 {
+            return f64::INFINITY;
+        }
+        child.reward / child.visits as f64
+            + EXPLORATION * ((parent.visits as f64).ln() / child.visits as f64).sqrt()
+    }
+
+    // one selection, expansion, rollout and backup - the depth of
+    // the new node
+    fn iterate(&mut self, root: &Deck) -> usize {
+        let mut deck = root.clone();
+        let mut node = 0;
+        let mut moves = vec![];
+        let mut seen: HashSet<Position, PassThrough> = HashSet::default();
+        seen.insert(deck.position(deck.hash()));
+
+        // selection
+        loop {
+            if self.nodes[node].untried.is_none() {
+                let mut untried = vec![];
+                if !deck.is_won() && moves.len() < MAX_MOVES - 1 {
+                    deck.get_moves(&mut untried);
+                }
+                self.nodes[node].untried = Some(untried);
+            }
+            let current = &self.nodes[node];
+            if !current.untried.as_ref().unwrap().is_empty() || current.children.is_empty() {
+                break;
+            }
+            let best = *current
+                .children
+                .iter()
+                .max_by(|a, b| self.uct(node, **a).total_cmp(&self.uct(node, **b)))
+                .unwrap();
+            node = best;
+            let m = self.nodes[node].m.unwrap();
+            deck = deck.apply_move(&m);
+            moves.push(m);
+            seen.insert(deck.position(deck.hash()));
+        }
+
+        // expansion, moves back to a position on the way are dropped
+        loop {
+            let untried = self.nodes[node].untried.as_mut().unwrap();
+            if untried.is_empty() {
+                break;
+            }
+            let m = untried.swap_remove(self.rng.below(untried.len()));
+            let next = deck.apply_move(&m);
+            if !seen.insert(next.position(next.hash())) {
+                continue;
+            }
+            self.nodes.push(Node {
+                m: Some(m),
+                parent: node,
+                children: vec![],
+                untried: None,
+                visits: 0,
+                reward: 0.,
+            });
+            let child = self.nodes.len() - 1;
+            self.nodes[node].children.push(child);
+            node = child;
+            deck = next;
+            moves.push(m);
+            break;
+        }
+        let depth = moves.len();
+
+        // rollout
+        while !deck.is_won() && moves.len() < MAX_MOVES - 1 {
+            let mut candidates = vec![];
+            deck.get_moves(&mut candidates);
+            let mut children: Vec<(Move, Deck)> = candidates
+                .iter()
+                .map(|m| (*m, deck.apply_move(m)))
+                .filter(|(_, next)| !seen.contains(&next.position(next.hash())))
+                .collect();
+            if children.is_empty() {
+                break;
+            }
+            let index = if self.rng.chance(EPSILON) {
+                self.rng.below(children.len())
+            } else {
+                let mut best = 0;
+                for i in 1..children.len() {
+                    if rollout_score(&children[i].1) > rollout_score(&children[best].1) {
+                        best = i;
+                    }
+                }
+                best
+            };
+            let (m, next) = children.swap_remove(index);
+            seen.insert(next.position(next.hash()));
+            deck = next;
+            moves.push(m);
+            self.rollout_moves += 1;
+        }
+
+        let value = reward(&deck, moves.len());
+        if deck.is_won()
+            && self
+                .best
+                .as_ref()
+                .is_none_or(|best| moves.len() < best.len())
+        {
+            self.best = Some(moves);
+        }
+
+        // backup
+        loop {
+            self.nodes[node].visits += 1;
+            self.nodes[node].reward += value;
+            if node == 0 {
+                break;
+            }
+            node = self.nodes[node].parent;
+        }
+        depth
+    }
+
+    // following the most visited children, what looks best so far
+    pub fn principal_line(&self) -> Vec<Move> {
+        let mut line = vec![];
+        let mut node = 0;
+        while let Some(child) = self.nodes.get(node).and_then(|node| {
+            node.children
+                .iter()
+                .max_by_key(|child| self.nodes[**child].visits)
+        }) {
+            node = *child;
+            line.push(self.nodes[node].m.unwrap());
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod mctstests {
+    use super::*;
+    use crate::deck::ENDGAME;
+
+    #[test]
+    fn endgame() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let mut mcts = Mcts::new(1);
+        let moves = mcts
+            .search(&deck, Some(50), None, &mut Observer::default())
+            .expect("won");
+        assert_eq!(mcts.iterations, 50);
+        assert_eq!(moves.len(), 3);
+        let mut won = deck.clone();
+        for m in &moves {
+            won = won.try_apply_move(m).expect("legal");
+        }
+        assert!(won.is_won());
+        assert!(!mcts.principal_line().is_empty());
+    }
+
+    #[test]
+    fn uct() {
+        let node = |visits, reward| Node {
+            m: None,
+            parent: 0,
+            children: vec![],
+            untried: Some(vec![]),
+            visits,
+            reward,
+        };
+        let mut mcts = Mcts::new(1);
+        mcts.nodes = vec![
+            node(20, 10.),
+            node(10, 9.),
+            node(0, 0.),
+            node(2, 1.8),
+            node(10, 1.),
+        ];
+        mcts.nodes[0].children = vec![1, 2, 3, 4];
+        // the unvisited child first, however good the others did
+        assert_eq!(mcts.uct(0, 2), f64::INFINITY);
+        assert!(mcts.uct(0, 2) > mcts.uct(0, 1));
+        // then by average reward, the less visited explored more
+        assert!(mcts.uct(0, 1) > mcts.uct(0, 4));
+        assert!(mcts.uct(0, 3) > mcts.uct(0, 1));
+    }
+}
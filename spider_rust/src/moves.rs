@@ -1,12 +1,21 @@
+use crate::deck::Deck;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+// serialized like the yaml solutions always looked
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Move {
-    off: bool,
-    talon: bool,
     from: u8,
     to: u8,
     index: u8,
+    #[serde(default, skip_serializing_if = "is_false")]
+    off: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    talon: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl fmt::Display for Move {
@@ -79,4 +88,129 @@ impl Move {
     pub fn is_invalid(&self) -> bool {
         self.from > 10
     }
+
+    // "3>8@5" moves the cards from index 5 of column 3 onto column 8,
+    // "deal" the next talon and "off 4" the finished suit of column 4.
+    // Columns count from 1 as in explain_move
+    pub fn notation(&self) -> String {
+        if self.talon {
+            return "deal".to_string();
+        }
+        if self.off {
+            return format!("off {}", self.from + 1);
+        }
+        format!("{}>{}@{}", self.from + 1, self.to + 1, self.index)
+    }
+
+    // the deck tells which talon is next and where a suit starts
+    pub fn parse(text: &str, deck: &Deck) -> Result<Move, String> {
+        let text = text.trim();
+        let column = |s: &str| match s.trim().parse::<usize>() {
+            Ok(c) if (1..=10).contains(&c) => Ok(c - 1),
+            _ => Err(format!("Invalid column '{}' in '{}'", s, text)),
+        };
+        if text == "deal" {
+            return match deck.next_talon() {
+                Some(talon) => Ok(Move::from_talon(talon)),
+                None => Err("No talon left to deal".to_string()),
+            };
+        }
+        if let Some(from) = text.strip_prefix("off") {
+            let from = column(from)?;
+            let count = deck.pile_count(from);
+            if count < 13 {
+                return Err(format!("Not enough cards to go off in '{}'", text));
+            }
+            return Ok(Move::off(from, count - 13));
+        }
+        let mut parts = text.splitn(2, '>');
+        let from = column(parts.next().unwrap_or(""))?;
+        let mut rest = parts
+            .next()
+            .ok_or_else(|| format!("Invalid move '{}'", text))?
+            .splitn(2, '@');
+        let to = column(rest.next().unwrap_or(""))?;
+        let index = match rest.next().map(|i| i.trim().parse::<usize>()) {
+            // a card of the pile, it's stored in a byte
+            Some(Ok(index)) if index < deck.pile_count(from) => index,
+            _ => return Err(format!("Invalid index in '{}'", text)),
+        };
+        Ok(Move::regular(from, to, index))
+    }
+
+    // moves separated by commas or lines, # starts a comment. Every
+    // move is checked and played, as the later ones depend on it
+    pub fn parse_list(text: &str, deck: &Deck) -> Result<Vec<Move>, String> {
+        let mut deck = deck.clone();
+        let mut moves = vec![];
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            for token in line.split(',') {
+                if token.trim().is_empty() {
+                    continue;
+                }
+                let m = Move::parse(token, &deck)?;
+                deck = match deck.try_apply_move(&m) {
+                    Ok(deck) => deck,
+                    Err(why) => {
+                        return Err(format!(
+                            "Move {} '{}' is illegal: {}",
+                            moves.len() + 1,
+                            token.trim(),
+                            why
+                        ))
+                    }
+                };
+                deck.reset_moves();
+                moves.push(m);
+            }
+        }
+        Ok(moves)
+    }
+}
+
+#[cfg(test)]
+mod movestests {
+    use super::*;
+
+    #[test]
+    fn notation() {
+        let text = "Play0: 7H 6H 5H AS
+        Play1:
+        Play2: KS
+        Play3: TH
+        Play4: |3S |9S |TS TH
+        Play5: |9S |9H 8H 4S
+        Play6: |7S |QS |KH |4H 3H 2S QH JH KH QH JS QS KS
+        Play7: |8S |JS |6S 7S
+        Play8: |6S |8S |AH |5S 4H 3H 2H AH
+        Play9: KH QH JH TH 9H 8H 7H 6H 5H 4H 3H 2H AH
+        Deal0: 4S 9H 5H 7H 5H TH JH JH 6H JH
+        Deal1:
+        Deal2:
+        Deal3:
+        Deal4:
+        Off: KS KH KS";
+        let deck = Deck::parse(&text.to_string());
+        for m in [Move::regular(0, 1, 3), Move::off(9, 0), Move::from_talon(0)].iter() {
+            assert_eq!(Move::parse(&m.notation(), &deck), Ok(*m));
+        }
+        assert_eq!(Move::regular(0, 1, 3).notation(), "1>2@3");
+        assert_eq!(Move::off(9, 0).notation(), "off 10");
+        assert!(Move::parse("0>2@3", &deck).is_err());
+        assert!(Move::parse("1>2", &deck).is_err());
+        // the first pile has 4 cards, and 259 would wrap around to 3
+        assert!(Move::parse("1>2@4", &deck).is_err());
+        assert!(Move::parse("1>2@259", &deck).is_err());
+        assert!(Move::parse("off 1", &deck).is_err());
+
+        assert_eq!(
+            Move::parse_list("1>2@3, deal # the only talon\n", &deck),
+            Ok(vec![Move::regular(0, 1, 3), Move::from_talon(0)])
+        );
+        // the empty column has to be filled before dealing
+        assert!(Move::parse_list("deal", &deck)
+            .unwrap_err()
+            .contains("empty piles"));
+    }
 }
@@ -0,0 +1,265 @@
+use crate::deck::{Deck, Position, MAX_MOVES};
+use crate::mcts::Rng;
+use crate::moves::Move;
+use crate::progress::{Observer, Progress};
+use crate::transposition::PassThrough;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+const LEVEL: usize = 3;
+// the searches one level down per level
+const ITERATIONS: usize = 100;
+const ALPHA: f64 = 1.0;
+
+// the weights of the moves by their code
+pub type Policy = HashMap<u64, f64>;
+
+// what a move does, packed into a number: its kind, the moved cards,
+// what is left on the source and where they go
+pub fn code(deck: &Deck, m: &Move) -> u64 {
+    if m.is_talon() {
+        return 1;
+    }
+    let from = deck.pile(m.from());
+    let hidden = from.hidden().min(5) as u64;
+    let reveals = m.index() > 0 && !from.at(m.index() - 1).faceup();
+    let empties = m.index() == 0;
+    let flags = reveals as u64 | (empties as u64) << 1;
+    if m.is_off() {
+        return 2 | flags << 2 | hidden << 4;
+    }
+    let card = from.at(m.index());
+    let length = (from.count() - m.index()).min(13) as u64;
+    let to = deck.pile(m.to());
+    let target = match to.count() {
+        0 => 0,
+        1..=3 => 1,
+        4..=7 => 2,
+        _ => 3,
+    };
+    let suit_match = to.count() > 0 && to.at(to.count() - 1).suit() == card.suit();
+    3 | flags << 2
+        | hidden << 4
+        | (card.rank() as u64) << 7
+        | length << 11
+        | target << 15
+        | (suit_match as u64) << 17
+}
+
+// wins above everything else, the shorter the better. Otherwise
+// what got into the off and what is playable
+fn score(deck: &Deck, length: usize) -> f64 {
+    if deck.is_won() {
+        return 1000. + (MAX_MOVES - length) as f64;
+    }
+    (deck.in_off() + deck.playable()) as f64
+}
+
+// the moves of a position the playouts choose from, none going back
+// to a position of the same playout
+fn choices(deck: &Deck, seen: &HashSet<Position, PassThrough>) -> Vec<(Move, Deck, u64)> {
+    if deck.is_won() {
+        return vec![];
+    }
+    let mut moves = vec![];
+    deck.get_moves(&mut moves);
+    moves
+        .iter()
+        .map(|m| (*m, deck.apply_move(m), code(deck, m)))
+        .filter(|(_, next, _)| !seen.contains(&next.position(next.hash())))
+        .collect()
+}
+
+// nested rollout policy adaptation: every level runs the level below
+// with a copy of its policy and moves the policy towards the best
+// sequence found so far
+pub struct Nrpa {
+    rng: Rng,
+    root: Deck,
+    playout_limit: Option<usize>,
+    deadline: Option<Instant>,
+    pub playouts: usize,
+    // moves played in all playouts
+    pub moves: usize,
+    pub best: Option<Vec<Move>>,
+}
+
+impl Nrpa {
+    pub fn new(
+        deck: &Deck,
+        seed: u64,
+        playout_limit: Option<usize>,
+        deadline: Option<Instant>,
+    ) -> Nrpa {
+        let mut root = deck.clone();
+        root.reset_moves();
+        Nrpa {
+            rng: Rng::new(seed),
+            root,
+            playout_limit,
+            deadline,
+            playouts: 0,
+            moves: 0,
+            best: None,
+        }
+    }
+
+    fn stopped(&self, observer: &Observer) -> bool {
+        observer.is_cancelled()
+            || self
+                .playout_limit
+                .is_some_and(|limit| self.playouts >= limit)
+            || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    // the shortest win of all playouts
+    pub fn search(&mut self, observer: &mut Observer) -> Option<Vec<Move>> {
+        let mut policy = Policy::new();
+        // the budget may end it before the last level is through
+        while !self.stopped(observer) {
+            self.level(LEVEL, &mut policy, observer);
+        }
+        self.best.clone()
+    }
+
+    fn level(
+        &mut self,
+        level: usize,
+        policy: &mut Policy,
+        observer: &mut Observer,
+    ) -> (f64, Vec<Move>) {
+        if level == 0 {
+            return self.playout(policy);
+        }
+        let mut best = (f64::MIN, vec![]);
+        for _ in 0..ITERATIONS {
+            if self.stopped(observer) {
+                break;
+            }
+            let result = self.level(level - 1, &mut policy.clone(), observer);
+            if result.0 >= best.0 {
+                best = result;
+            }
+            self.adapt(policy, &best.1);
+            if level == LEVEL {
+                observer.report(Progress {
+                    depth: level,
+                    frontier: self.playouts,
+                    best: self.best.as_ref().map(|moves| moves.len()),
+                });
+            }
+        }
+        best
+    }
+
+    fn playout(&mut self, policy: &Policy) -> (f64, Vec<Move>) {
+        self.playouts += 1;
+        let mut deck = self.root.clone();
+        let mut seen: HashSet<Position, PassThrough> = HashSet::default();
+        seen.insert(deck.position(deck.hash()));
+        let mut sequence = vec![];
+        while sequence.len() < MAX_MOVES - 1 {
+            let mut choices = choices(&deck, &seen);
+            if choices.is_empty() {
+                break;
+            }
+            let weights: Vec<f64> = choices
+                .iter()
+                .map(|(_, _, code)| policy.get(code).copied().unwrap_or(0.).exp())
+                .collect();
+            let mut pick =
+                (self.rng.next() % 1_000_000) as f64 / 1_000_000. * weights.iter().sum::<f64>();
+            let mut index = weights.len() - 1;
+            for (i, weight) in weights.iter().enumerate() {
+                if pick < *weight {
+                    index = i;
+                    break;
+                }
+                pick -= weight;
+            }
+            let (m, next, _) = choices.swap_remove(index);
+            seen.insert(next.position(next.hash()));
+            deck = next;
+            sequence.push(m);
+            self.moves += 1;
+        }
+        if deck.is_won()
+            && self
+                .best
+                .as_ref()
+                .is_none_or(|best| sequence.len() < best.len())
+        {
+            self.best = Some(sequence.clone());
+        }
+        (score(&deck, sequence.len()), sequence)
+    }
+
+    // the moves of the sequence more likely, the others they were
+    // chosen over less, by how likely they were
+    fn adapt(&self, policy: &mut Policy, sequence: &[Move]) {
+        let old = policy.clone();
+        let mut deck = self.root.clone();
+        let mut seen: HashSet<Position, PassThrough> = HashSet::default();
+        seen.insert(deck.position(deck.hash()));
+        for m in sequence {
+            let choices = choices(&deck, &seen);
+            let total: f64 = choices
+                .iter()
+                .map(|(_, _, code)| old.get(code).copied().unwrap_or(0.).exp())
+                .sum();
+            for (other, _, code) in &choices {
+                let weight = old.get(code).copied().unwrap_or(0.).exp();
+                let entry = policy.entry(*code).or_insert(0.);
+                *entry -= ALPHA * weight / total;
+                if other == m {
+                    *entry += ALPHA;
+                }
+            }
+            deck = deck.apply_move(m);
+            seen.insert(deck.position(deck.hash()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod nrpatests {
+    use super::*;
+    use crate::deck::ENDGAME;
+
+    #[test]
+    fn endgame() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        // 9H onto its suit or into a free play
+        let on_suit = code(&deck, &Move::regular(1, 0, 0));
+        let free = code(&deck, &Move::regular(1, 3, 0));
+        assert_ne!(on_suit, free);
+        assert_eq!(on_suit & 3, 3);
+        assert_eq!(code(&deck, &Move::from_talon(0)), 1);
+
+        let mut nrpa = Nrpa::new(&deck, 1, Some(200), None);
+        let moves = nrpa.search(&mut Observer::default()).expect("won");
+        assert_eq!(nrpa.playouts, 200);
+        assert_eq!(moves.len(), 3);
+        let mut won = deck.clone();
+        for m in &moves {
+            won = won.try_apply_move(m).expect("legal");
+        }
+        assert!(won.is_won());
+    }
+
+    #[test]
+    fn adapt() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let nrpa = Nrpa::new(&deck, 1, None, None);
+        let on_suit = Move::regular(1, 0, 0);
+        let mut policy = Policy::new();
+        nrpa.adapt(&mut policy, &[on_suit]);
+        // the chosen move gains what the others lose
+        let chosen = code(&deck, &on_suit);
+        assert!(policy[&chosen] > 0.);
+        assert!(policy
+            .iter()
+            .all(|(code, weight)| *code == chosen || *weight < 0.));
+        assert!(policy.values().sum::<f64>().abs() < 1e-9);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::card::Card;
-use once_cell::sync::Lazy;
 use seahash;
+use std::cell::{Cell, UnsafeCell};
 use std::cmp::Ordering;
 use std::mem::MaybeUninit;
 use std::rc::Rc;
@@ -39,6 +39,7 @@ impl PileTree {
                 under: 0,
                 hidden: 0,
                 order: 0,
+                breaks: 0,
             }),
         }
     }
@@ -64,21 +65,28 @@ impl PileTree {
                 break;
             }
         }
+        // the bytes past the count are left over from whoever built the
+        // pile first, clear them so the hash only sees the cards
+        let mut bytes = [0; MAX_CARDS];
+        bytes[..=index].copy_from_slice(&cards[..=index]);
         let mut newpile = Pile {
-            cards: *cards,
+            cards: bytes,
             count: index + 1,
             chaos: 0,
             playable: 0,
             under: 0,
             hidden: 0,
             order: 0,
+            breaks: 0,
         };
         newpile.chaos = newpile.calculate_chaos();
         newpile.playable = newpile.calculate_playable();
         newpile.under = newpile.calculate_under(0) as u32;
         newpile.hidden = newpile.calculate_hidden();
         newpile.order = newpile.calculate_order();
+        newpile.breaks = newpile.calculate_breaks();
 
+        PILE_COUNT.with(|piles| piles.set(piles.get() + 1));
         tree.children[cards[index] as usize] = Some(Box::new(PileTree {
             pile: Rc::new(newpile),
             children: PileTree::nones(),
@@ -96,7 +104,12 @@ impl PileTree {
     */
 }
 
-static mut PILE_TREE: Lazy<PileTree> = Lazy::new(|| PileTree::new());
+thread_local! {
+    // the piles are shared through Rc, so every thread interns its own.
+    // Not a RefCell, calculate_under interns while a pile is inserted
+    static PILE_TREE: UnsafeCell<PileTree> = UnsafeCell::new(PileTree::new());
+    static PILE_COUNT: Cell<usize> = const { Cell::new(0) };
+}
 
 pub struct Pile {
     cards: [u8; MAX_CARDS],
@@ -106,6 +119,7 @@ pub struct Pile {
     playable: u8,
     hidden: u8,
     order: u8,
+    breaks: u8,
 }
 
 impl PartialEq for Pile {
@@ -147,11 +161,26 @@ impl PartialOrd for Pile {
 
 impl Pile {
     pub fn or_insert(cards: &[u8; MAX_CARDS], count: usize) -> Rc<Pile> {
-        unsafe { PileTree::insert_pile(&mut PILE_TREE, cards, count, 0) }
+        PILE_TREE.with(|tree| unsafe { PileTree::insert_pile(&mut *tree.get(), cards, count, 0) })
+    }
+
+    // starts the thread over with an empty tree, so a search doesn't
+    // depend on the piles earlier ones interned. Piles still around
+    // stay valid, they are just not found again: the same cards get a
+    // new Rc, so a Position or a TranspositionTable from before never
+    // matches a deck built after. Only call it between searches
+    pub fn reset_tree() {
+        PILE_TREE.with(|tree| unsafe { *tree.get() = PileTree::new() });
+        PILE_COUNT.with(|piles| piles.set(0));
+    }
+
+    // the piles in this thread's tree
+    pub fn interned() -> usize {
+        PILE_COUNT.with(|piles| piles.get())
     }
 
     pub fn empty() -> Rc<Pile> {
-        unsafe { Rc::clone(&PILE_TREE.pile) }
+        PILE_TREE.with(|tree| unsafe { Rc::clone(&(*tree.get()).pile) })
     }
 
     pub fn at(&self, index: usize) -> Card {
@@ -377,6 +406,29 @@ impl Pile {
         result
     }
 
+    pub fn breaks(&self) -> u32 {
+        self.breaks as u32
+    }
+
+    // neighbours that are not in suit and rank order, no matter if face
+    // up or not - each needs a move to separate them. Cards on top of a
+    // king are left out, the king can only leave together with them
+    fn calculate_breaks(&self) -> u8 {
+        let mut result = 0;
+        for i in 1..self.count {
+            let lower = self.at(i - 1);
+            let upper = self.at(i);
+            // guessing can't tell
+            if lower.rank() == 0 || upper.rank() == 0 || upper.rank() == 13 {
+                continue;
+            }
+            if lower.suit() != upper.suit() || lower.rank() != upper.rank() + 1 {
+                result += 1;
+            }
+        }
+        result
+    }
+
     pub fn order(&self) -> u8 {
         self.order
     }
@@ -430,6 +482,10 @@ impl Pile {
         }
     }
 
+    pub fn has_unknown(&self) -> bool {
+        (0..self.count).any(|i| self.at(i).is_unknown())
+    }
+
     pub fn pick_unknown(&self, cards: &mut Vec<Card>) -> Rc<Pile> {
         let mut newcards = self.cards.clone();
         for i in 0..self.count {
@@ -549,6 +605,17 @@ mod piletests {
         assert_eq!(pile.calculate_under(0), 1);
     }
 
+    #[test]
+    fn breaks() {
+        // |6S->|3H, |3H->8S and 6S->4H, the king starts its own run
+        let pile = Pile::parse("|6S |3H 8S..6s 4h..3h").expect("parsed");
+        assert_eq!(pile.breaks(), 3);
+        let pile = Pile::parse("|9S 8S..6S |2H KH QH").expect("parsed");
+        assert_eq!(pile.breaks(), 1);
+        let pile = Pile::parse("|XX |XX 5S").expect("parsed");
+        assert_eq!(pile.breaks(), 0);
+    }
+
     #[test]
     fn order() {
         // only 7s+6s and 3h are on top of the right card
@@ -0,0 +1,87 @@
+use crate::stats::SearchStats;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// where a long search is, after every depth or pick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub depth: usize,
+    pub frontier: usize,
+    // the shortest win found so far
+    pub best: Option<usize>,
+}
+
+pub type ProgressCallback<'a> = Box<dyn FnMut(&Progress) + 'a>;
+
+// clones share the flag, so one can be handed to a signal handler
+// or another thread while the search checks the other
+#[derive(Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn new() -> Cancel {
+        Cancel::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// what the caller follows a search with: the counters, a callback
+// for the progress and the token to stop it between depths
+#[derive(Default)]
+pub struct Observer<'a> {
+    pub stats: SearchStats,
+    pub progress: Option<ProgressCallback<'a>>,
+    pub cancel: Cancel,
+}
+
+impl<'a> Observer<'a> {
+    pub fn new(cancel: Cancel) -> Observer<'a> {
+        Observer {
+            stats: SearchStats::default(),
+            progress: None,
+            cancel,
+        }
+    }
+
+    pub fn report(&mut self, progress: Progress) {
+        if let Some(callback) = &mut self.progress {
+            callback(&progress);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+}
+
+#[cfg(test)]
+mod progresstests {
+    use super::*;
+
+    #[test]
+    fn cancel() {
+        let cancel = Cancel::new();
+        let mut reported = vec![];
+        {
+            let mut observer = Observer::new(cancel.clone());
+            observer.progress = Some(Box::new(|progress: &Progress| reported.push(*progress)));
+            observer.report(Progress {
+                depth: 1,
+                frontier: 20,
+                best: None,
+            });
+            assert!(!observer.is_cancelled());
+            cancel.cancel();
+            assert!(observer.is_cancelled());
+        }
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].frontier, 20);
+    }
+}
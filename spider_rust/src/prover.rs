@@ -0,0 +1,170 @@
+use crate::deck::Deck;
+use crate::evaluator::{DefaultEvaluator, Evaluator};
+use crate::ida::MoveGenerator;
+use crate::moves::Move;
+use crate::transposition::TranspositionTable;
+use std::cmp::Reverse;
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Won(Vec<Move>),
+    // every position reachable was visited, none of them is won
+    Lost,
+    // the node budget ran out first
+    Unknown,
+}
+
+struct Frame {
+    deck: Deck,
+    moves: Vec<Move>,
+    next: usize,
+}
+
+// exhaustive depth first search over the generated moves. Whether a
+// position can be won doesn't depend on the way there, so every
+// position is visited only once. Only with Deck::get_all_moves is
+// Lost a proof
+pub struct Prover {
+    generator: MoveGenerator,
+    max_nodes: usize,
+    nodes: usize,
+}
+
+impl Prover {
+    pub fn new(generator: MoveGenerator, max_nodes: usize) -> Prover {
+        Prover {
+            generator,
+            max_nodes,
+            nodes: 0,
+        }
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    fn frame(&mut self, deck: Deck) -> Frame {
+        self.nodes += 1;
+        let mut moves = vec![];
+        (self.generator)(&deck, &mut moves);
+        // the most promising first, it's only about finding a win early
+        let mut scored: Vec<_> = moves
+            .into_iter()
+            .map(|m| (DefaultEvaluator.score(&deck.apply_move(&m)), m))
+            .collect();
+        scored.sort_by_key(|(score, _)| Reverse(*score));
+        Frame {
+            deck,
+            moves: scored.into_iter().map(|(_, m)| m).collect(),
+            next: 0,
+        }
+    }
+
+    pub fn prove(&mut self, deck: &Deck) -> Verdict {
+        let mut root = deck.clone();
+        root.reset_moves();
+        if root.is_won() {
+            return Verdict::Won(vec![]);
+        }
+        let mut seen = TranspositionTable::new();
+        seen.insert(&root, root.hash(), 0);
+        let mut stack = vec![self.frame(root)];
+
+        while let Some(frame) = stack.last_mut() {
+            if frame.next == frame.moves.len() {
+                stack.pop();
+                continue;
+            }
+            let m = frame.moves[frame.next];
+            frame.next += 1;
+            let mut newdeck = frame.deck.apply_move(&m);
+            // the path is on the stack, don't let the deck run out of moves
+            newdeck.reset_moves();
+            if !seen.insert(&newdeck, newdeck.hash(), 0) {
+                continue;
+            }
+            if newdeck.is_won() {
+                let path: Vec<Move> = stack.iter().map(|f| f.moves[f.next - 1]).collect();
+                return Verdict::Won(path);
+            }
+            if self.nodes >= self.max_nodes {
+                return Verdict::Unknown;
+            }
+            let frame = self.frame(newdeck);
+            stack.push(frame);
+        }
+        Verdict::Lost
+    }
+}
+
+#[cfg(test)]
+mod provertests {
+    use super::*;
+    use crate::deck::ENDGAME;
+
+    #[test]
+    fn won() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let mut prover = Prover::new(Deck::get_all_moves, 1000);
+        match prover.prove(&deck) {
+            Verdict::Won(moves) => {
+                let mut deck = deck.clone();
+                for m in &moves {
+                    deck = deck.apply_move(m);
+                }
+                assert!(deck.is_won());
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn lost() {
+        // no top card fits on another and nothing is left to deal
+        let text = "Play0: |KS |QS AS
+        Play1: |KH |QH AH
+        Play2: |JS |JH 3S
+        Play3: |TS |TH 3H
+        Play4: |8S |8H 5S
+        Play5: |6S |6H 5H
+        Play6: |4S |4H 7S
+        Play7: |2S 7H
+        Play8: |2H 9S
+        Play9: 9H
+        Deal0:
+        Deal1:
+        Deal2:
+        Deal3:
+        Deal4:
+        Off: KS KS KS KH KH KH";
+        let deck = Deck::parse(&text.to_string());
+        let mut prover = Prover::new(Deck::get_all_moves, 1000);
+        assert_eq!(prover.prove(&deck), Verdict::Lost);
+        assert_eq!(prover.nodes(), 1);
+    }
+
+    #[test]
+    fn lost_after_moves() {
+        // the twos go onto the threes and back, nothing else turns up
+        let text = "Play0: |KS |QS AS
+        Play1: |KH |QH AH
+        Play2: |JS |JH 3S
+        Play3: |TS |TH 3H
+        Play4: |8S |8H 5S
+        Play5: |6S |6H 5H
+        Play6: |4S |4H 7S
+        Play7: |7H 2S
+        Play8: |9S 2H
+        Play9: 9H
+        Deal0:
+        Deal1:
+        Deal2:
+        Deal3:
+        Deal4:
+        Off: KS KS KS KH KH KH";
+        let deck = Deck::parse(&text.to_string());
+        let mut prover = Prover::new(Deck::get_all_moves, 1000);
+        assert_eq!(prover.prove(&deck), Verdict::Lost);
+        assert!(prover.nodes() > 1);
+    }
+}
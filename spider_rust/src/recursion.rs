@@ -0,0 +1,183 @@
+use crate::deck::{Deck, MAX_MOVES};
+use crate::evaluator::Evaluator;
+use crate::moves::Move;
+use crate::progress::{Observer, Progress};
+use crate::transposition::{SeenLimit, TranspositionTable};
+use std::time::Instant;
+
+// the recursion of the C++ solver: the moves are tried in the order
+// of what the beam search estimates for them. As the estimates aren't
+// perfect, moves up to slack worse than the best solution are still
+// followed - and once less than trust moves are left, the estimate is
+// taken as it is
+pub struct Recursion<'a, E: Evaluator + ?Sized> {
+    evaluator: &'a E,
+    cap: usize,
+    slack: usize,
+    trust: u32,
+    seen_limit: Option<SeenLimit>,
+    pub best: usize,
+    pub best_moves: Vec<Move>,
+    // the beam searches run
+    pub searches: usize,
+    // gives up like a cancel once passed
+    pub deadline: Option<Instant>,
+}
+
+impl<'a, E: Evaluator + ?Sized> Recursion<'a, E> {
+    pub fn new(
+        evaluator: &'a E,
+        cap: usize,
+        slack: usize,
+        trust: u32,
+        seen_limit: Option<SeenLimit>,
+    ) -> Recursion<'a, E> {
+        Recursion {
+            evaluator,
+            cap,
+            slack,
+            trust,
+            seen_limit,
+            best: MAX_MOVES,
+            best_moves: vec![],
+            searches: 0,
+            deadline: None,
+        }
+    }
+
+    // the moves of the best solution found, none without one
+    pub fn search(&mut self, deck: &Deck, observer: &mut Observer) -> Option<Vec<Move>> {
+        let mut deck = deck.clone();
+        deck.reset_moves();
+        self.recurse(&deck, observer);
+        if self.best_moves.is_empty() {
+            None
+        } else {
+            Some(self.best_moves.clone())
+        }
+    }
+
+    fn stopped(&self, observer: &Observer) -> bool {
+        observer.is_cancelled()
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn recurse(&mut self, orig: &Deck, observer: &mut Observer) {
+        let depth = orig.get_moves_index();
+        let mut moves = vec![];
+        orig.get_moves(&mut moves);
+        // the estimate, the move and the line the estimate came from
+        let mut queue = vec![];
+        for m in moves {
+            if self.stopped(observer) {
+                return;
+            }
+            let mut newdeck = orig.apply_move(&m);
+            if newdeck.is_won() {
+                queue.push((0, m, newdeck.win_moves()));
+                continue;
+            }
+            self.searches += 1;
+            let mut child_observer = Observer::new(observer.cancel.clone());
+            let won = newdeck.shortest_path_observed(
+                self.evaluator,
+                self.cap,
+                false,
+                None,
+                &mut TranspositionTable::with_limit(self.seen_limit),
+                &mut child_observer,
+            );
+            observer.stats.add_counts(&child_observer.stats);
+            // no win found - end of story
+            if let Some(estimate) = won.filter(|moves| *moves > 0) {
+                queue.push((estimate as u32, m, newdeck.win_moves()));
+            }
+        }
+        queue.sort_by_key(|(estimate, _, _)| *estimate);
+        observer.report(Progress {
+            depth,
+            frontier: queue.len(),
+            best: Some(self.best).filter(|best| *best < MAX_MOVES),
+        });
+
+        for (estimate, m, line) in queue {
+            if self.stopped(observer) {
+                return;
+            }
+            let total = depth + 1 + estimate as usize;
+            // the queue is sorted, the rest needs even more
+            if total > self.best + self.slack {
+                break;
+            }
+            if total < self.best {
+                self.best = total;
+                self.best_moves = line;
+                observer.report(Progress {
+                    depth,
+                    frontier: 0,
+                    best: Some(total),
+                });
+            }
+            if estimate < self.trust {
+                // let's trust the beam search on this one
+                return;
+            }
+            let newdeck = orig.apply_move(&m);
+            self.recurse(&newdeck, observer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod recursiontests {
+    use super::*;
+    use crate::deck::{ENDGAME, NINE_MOVES};
+    use crate::evaluator::DefaultEvaluator;
+
+    #[test]
+    fn endgame() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        // trusting the first estimate
+        let mut recursion = Recursion::new(&DefaultEvaluator, 10, 5, 20, None);
+        let moves = recursion
+            .search(&deck, &mut Observer::default())
+            .expect("won");
+        assert_eq!(moves.len(), 3);
+        assert_eq!(recursion.best, 3);
+        let searches = recursion.searches;
+
+        // following every move down to the win
+        let mut recursion = Recursion::new(&DefaultEvaluator, 10, 5, 0, None);
+        let moves = recursion
+            .search(&deck, &mut Observer::default())
+            .expect("won");
+        assert_eq!(moves.len(), 3);
+        assert!(recursion.searches > searches);
+        let mut won = deck.clone();
+        for m in &moves {
+            won = won.try_apply_move(m).expect("legal");
+        }
+        assert!(won.is_won());
+    }
+
+    #[test]
+    fn slack_and_trust() {
+        let deck = Deck::parse(&NINE_MOVES.to_string());
+        let searches = |slack, trust| {
+            let mut recursion = Recursion::new(&DefaultEvaluator, 5, slack, trust, None);
+            let moves = recursion.search(&deck, &mut Observer::default());
+            assert_eq!(moves.map(|moves| moves.len()), Some(9));
+            recursion.searches
+        };
+        // the estimates of the first moves are all trusted
+        let trusted = searches(0, 20);
+        assert_eq!(trusted, 5);
+        // down to where less than 4 moves are left
+        let followed = searches(0, 4);
+        assert!(followed > trusted);
+        // and the moves one worse than the best as well
+        assert!(searches(1, 4) > followed);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::deck::Deck;
+use crate::progress::Observer;
+use crate::solution::Solution;
+use crate::solver::{Budget, Solver};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// a solution in the results directory, named like example7.game.500.yaml
+pub struct Archived {
+    pub game: String,
+    pub cap: usize,
+    pub path: String,
+}
+
+pub fn collect_results(dir: &Path) -> Vec<Archived> {
+    let mut results = vec![];
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|why| panic!("couldn't read {}: {}", dir.display(), why))
+        .map(|entry| entry.expect("directory entry").path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if let Some((game, cap)) = split_name(&name) {
+            results.push(Archived {
+                game,
+                cap,
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+    }
+    // the games in order, each with its caps from small to large
+    results.sort_by(|a, b| (&a.game, a.cap).cmp(&(&b.game, b.cap)));
+    results
+}
+
+fn split_name(name: &str) -> Option<(String, usize)> {
+    let rest = name.strip_suffix(".yaml")?;
+    let dot = rest.rfind('.')?;
+    let cap = rest[dot + 1..].parse().ok()?;
+    let game = &rest[..dot];
+    if !game.ends_with(".game") {
+        return None;
+    }
+    Some((game.to_string(), cap))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Shorter,
+    Equal,
+    Longer,
+    // no win anymore
+    Missing,
+}
+
+impl Change {
+    pub fn of(stored: usize, new: Option<usize>) -> Change {
+        match new {
+            None => Change::Missing,
+            Some(new) if new < stored => Change::Shorter,
+            Some(new) if new == stored => Change::Equal,
+            Some(_) => Change::Longer,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Change::Shorter => "shorter",
+            Change::Equal => "equal",
+            Change::Longer => "longer",
+            Change::Missing => "missing",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+pub struct Regression {
+    pub game: String,
+    pub cap: usize,
+    pub stored: usize,
+    pub new: Option<usize>,
+    pub change: Change,
+}
+
+// solves the games again at the caps their results were recorded
+// with and compares the lengths. Results above max_cap are skipped
+pub fn regress(
+    results: &[Archived],
+    games: &Path,
+    suits: usize,
+    seed: u64,
+    max_cap: Option<usize>,
+    solver: &mut dyn Solver,
+    budget: &Budget,
+) -> Vec<Regression> {
+    let mut regressions = vec![];
+    println!(
+        "{:<16} {:>6} {:>6} {:>6} change",
+        "game", "cap", "stored", "new"
+    );
+    for archived in results {
+        if max_cap.is_some_and(|max| archived.cap > max) {
+            continue;
+        }
+        let contents = fs::read_to_string(&archived.path)
+            .unwrap_or_else(|why| panic!("couldn't read {}: {}", archived.path, why));
+        let stored = Solution::parse(&contents)
+            .unwrap_or_else(|e| panic!("{}: {}", archived.path, e))
+            .moves
+            .len();
+        let filename = games.join(&archived.game);
+        let contents = fs::read_to_string(&filename)
+            .unwrap_or_else(|why| panic!("couldn't read {}: {}", filename.display(), why));
+        let mut deck = Deck::parse(&contents);
+        deck.shuffle_unknowns_with_seed(suits, seed);
+        let budget = Budget {
+            cap: archived.cap,
+            ..*budget
+        };
+        let new = solver
+            .solve(&deck, &budget, &mut Observer::default())
+            .moves
+            .map(|moves| moves.len());
+        let regression = Regression {
+            game: archived.game.clone(),
+            cap: archived.cap,
+            stored,
+            new,
+            change: Change::of(stored, new),
+        };
+        println!(
+            "{:<16} {:>6} {:>6} {:>6} {}",
+            regression.game,
+            regression.cap,
+            regression.stored,
+            regression
+                .new
+                .map_or("-".to_string(), |moves| moves.to_string()),
+            regression.change
+        );
+        regressions.push(regression);
+    }
+    regressions
+}
+
+pub fn summary(regressions: &[Regression]) -> String {
+    let mut result = String::new();
+    let mut caps: Vec<usize> = regressions.iter().map(|r| r.cap).collect();
+    caps.sort_unstable();
+    caps.dedup();
+    result += &format!(
+        "{:>6} {:>8} {:>6} {:>7} {:>8} {:>13} {:>10}\n",
+        "cap", "shorter", "equal", "longer", "missing", "stored moves", "new moves"
+    );
+    for cap in caps {
+        let at_cap: Vec<_> = regressions.iter().filter(|r| r.cap == cap).collect();
+        let count = |change| at_cap.iter().filter(|r| r.change == change).count();
+        // only the games won both times are comparable
+        let both: Vec<_> = at_cap.iter().filter(|r| r.new.is_some()).collect();
+        result += &format!(
+            "{:>6} {:>8} {:>6} {:>7} {:>8} {:>13} {:>10}\n",
+            cap,
+            count(Change::Shorter),
+            count(Change::Equal),
+            count(Change::Longer),
+            count(Change::Missing),
+            both.iter().map(|r| r.stored).sum::<usize>(),
+            both.iter().map(|r| r.new.unwrap()).sum::<usize>()
+        );
+    }
+    result
+}
+
+#[cfg(test)]
+mod regresstests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            split_name("example36.game.5000.yaml"),
+            Some(("example36.game".to_string(), 5000))
+        );
+        assert_eq!(split_name("example36.game.yaml"), None);
+        assert_eq!(split_name("notes.txt"), None);
+        let results = collect_results(Path::new("results"));
+        assert_eq!(results.len(), 153);
+        assert_eq!(results[0].game, "example.game");
+        assert_eq!(results[0].cap, 500);
+        assert_eq!(results[2].cap, 50000);
+    }
+
+    #[test]
+    fn changes() {
+        assert_eq!(Change::of(100, Some(90)), Change::Shorter);
+        assert_eq!(Change::of(100, Some(100)), Change::Equal);
+        assert_eq!(Change::of(100, Some(101)), Change::Longer);
+        assert_eq!(Change::of(100, None), Change::Missing);
+        let regressions = vec![
+            Regression {
+                game: "a.game".to_string(),
+                cap: 500,
+                stored: 100,
+                new: Some(90),
+                change: Change::Shorter,
+            },
+            Regression {
+                game: "b.game".to_string(),
+                cap: 500,
+                stored: 120,
+                new: None,
+                change: Change::Missing,
+            },
+        ];
+        let summary = summary(&regressions);
+        let line = summary.lines().nth(1).unwrap();
+        let numbers: Vec<&str> = line.split_whitespace().collect();
+        assert_eq!(numbers, ["500", "1", "0", "0", "1", "100", "90"]);
+    }
+}
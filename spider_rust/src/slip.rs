@@ -0,0 +1,179 @@
+use crate::deck::Deck;
+use crate::evaluator::Evaluator;
+use crate::moves::Move;
+use crate::transposition::{SeenLimit, TranspositionTable};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChildStatus {
+    Searched,
+    // reached before at the same depth or shallower
+    Seen,
+    // its lower bound can't beat the best solution
+    Pruned,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChildEstimate {
+    #[serde(flatten)]
+    pub m: Move,
+    pub status: ChildStatus,
+    // the moves the beam search needed after this one, none for no win
+    #[serde(default)]
+    pub estimate: Option<u32>,
+}
+
+// a position of the A+ search where every move needed more than the
+// position was estimated at - the estimates went wrong somewhere
+#[derive(Serialize, Deserialize)]
+pub struct SlipReport {
+    pub game: String,
+    pub cap: usize,
+    pub suits: usize,
+    pub seed: Option<u64>,
+    pub depth: u32,
+    // the moves the beam search needed from the position
+    pub estimate: u32,
+    // the moves the best searched child needed, its move included
+    pub best: u32,
+    pub position: Deck,
+    pub children: Vec<ChildEstimate>,
+}
+
+impl SlipReport {
+    // named after what the moves needed and what was estimated
+    pub fn filename(&self) -> String {
+        format!(
+            "slip.{}-{}.yaml",
+            self.depth + self.best,
+            self.depth + self.estimate
+        )
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = serde_yaml::to_string(self).expect("serializable");
+        fs::write(path, text).map_err(|why| format!("couldn't write {}: {}", path, why))
+    }
+
+    pub fn parse(contents: &str) -> Result<SlipReport, String> {
+        serde_yaml::from_str(contents).map_err(|e| format!("Invalid slip report: {}", e))
+    }
+}
+
+// the best child, counting its own move
+pub fn best_child(estimates: &[Option<u32>]) -> Option<u32> {
+    estimates
+        .iter()
+        .flatten()
+        .map(|estimate| estimate + 1)
+        .min()
+}
+
+pub struct Rerun {
+    pub estimate: Option<u32>,
+    // in the order of the report's children
+    pub children: Vec<Option<u32>>,
+}
+
+impl Rerun {
+    // the slip is there again if the best move needs more than the
+    // position. Without an estimate for the position it's no slip
+    pub fn reproduces(&self) -> bool {
+        match (self.estimate, best_child(&self.children)) {
+            (Some(estimate), Some(best)) => best > estimate,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+fn estimate<E: Evaluator + ?Sized>(
+    deck: &Deck,
+    evaluator: &E,
+    cap: usize,
+    seen_limit: Option<SeenLimit>,
+) -> Option<u32> {
+    let mut deck = deck.clone();
+    deck.reset_moves();
+    deck.shortest_path_with(
+        evaluator,
+        cap,
+        false,
+        None,
+        &mut TranspositionTable::with_limit(seen_limit),
+    )
+    .filter(|moves| *moves > 0)
+    .map(|moves| moves as u32)
+}
+
+// searches the position and every child again, the pruned and seen
+// ones included, with the cap of the report
+pub fn rerun<E: Evaluator + ?Sized>(
+    report: &SlipReport,
+    evaluator: &E,
+    seen_limit: Option<SeenLimit>,
+) -> Result<Rerun, String> {
+    let position = &report.position;
+    let mut children = vec![];
+    for child in &report.children {
+        let deck = position
+            .try_apply_move(&child.m)
+            .map_err(|why| format!("{} is illegal: {}", child.m.notation(), why))?;
+        children.push(estimate(&deck, evaluator, report.cap, seen_limit));
+    }
+    Ok(Rerun {
+        estimate: estimate(position, evaluator, report.cap, seen_limit),
+        children,
+    })
+}
+
+#[cfg(test)]
+mod sliptests {
+    use super::*;
+    use crate::deck::ENDGAME;
+    use crate::evaluator::DefaultEvaluator;
+
+    #[test]
+    fn report_and_rerun() {
+        let position = Deck::parse(&ENDGAME.to_string());
+        let mut moves = vec![];
+        position.get_moves(&mut moves);
+        let children: Vec<ChildEstimate> = moves
+            .iter()
+            .map(|m| ChildEstimate {
+                m: *m,
+                status: ChildStatus::Searched,
+                estimate: Some(4),
+            })
+            .collect();
+        let report = SlipReport {
+            game: "endgame.game".to_string(),
+            cap: 10,
+            suits: 1,
+            seed: None,
+            depth: 7,
+            estimate: 3,
+            best: 5,
+            position,
+            children,
+        };
+        assert_eq!(report.filename(), "slip.12-10.yaml");
+        let text = serde_yaml::to_string(&report).unwrap();
+        let parsed = SlipReport::parse(&text).expect("parsed");
+        assert_eq!(parsed.children, report.children);
+        assert_eq!(parsed.position.to_string(), report.position.to_string());
+
+        // the endgame is won in 3, so is its best child in 2 more
+        let rerun = rerun(&parsed, &DefaultEvaluator, None).expect("legal");
+        assert_eq!(rerun.estimate, Some(3));
+        assert_eq!(best_child(&rerun.children), Some(3));
+        assert!(!rerun.reproduces());
+        let slipped = Rerun {
+            estimate: Some(3),
+            children: vec![Some(4), None],
+        };
+        assert!(slipped.reproduces());
+    }
+}
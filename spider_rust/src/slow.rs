@@ -0,0 +1,408 @@
+use crate::checkpoint::{Checkpoint, HeapEntry};
+use crate::deck::{Deck, MAX_MOVES};
+use crate::evaluator::Evaluator;
+use crate::moves::Move;
+use crate::progress::{Observer, Progress};
+use crate::slip::{ChildEstimate, ChildStatus, SlipReport};
+use crate::transposition::{SeenLimit, TranspositionTable};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+pub struct WeightedDeck {
+    pub deck: Deck,
+    pub depth: u32,
+    pub moves: u32,
+    pub total: u32,
+    pub hash: u64,
+}
+
+impl PartialOrd for WeightedDeck {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for WeightedDeck {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .total
+            .cmp(&self.total)
+            .then(self.depth.cmp(&other.depth))
+    }
+}
+
+impl PartialEq for WeightedDeck {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl Eq for WeightedDeck {}
+
+// the state of the A+ search, all a checkpoint needs to continue
+pub struct SlowSearch {
+    pub game: String,
+    pub cap: usize,
+    // of the deal, for the checkpoints and slip reports
+    pub suits: usize,
+    pub seed: Option<u64>,
+    pub deal: Deck,
+    pub heap: BinaryHeap<WeightedDeck>,
+    // never aged, it only holds the picked positions and their
+    // children. The seen limit is for the beam searches of a pick
+    pub seen: TranspositionTable,
+    pub best: usize,
+    pub best_moves: Vec<Move>,
+    // print the picks and write slip reports
+    pub verbose: bool,
+}
+
+impl SlowSearch {
+    // starts from the deal and the moves the beam search needed for it
+    pub fn new(game: &str, deal: &Deck, estimate: u32, cap: usize) -> SlowSearch {
+        let mut deal = deal.clone();
+        deal.reset_moves();
+        let mut heap = BinaryHeap::new();
+        heap.push(WeightedDeck {
+            hash: deal.hash(),
+            deck: deal.clone(),
+            depth: 0,
+            moves: estimate,
+            total: estimate,
+        });
+        SlowSearch {
+            game: game.to_string(),
+            cap,
+            suits: 0,
+            seed: None,
+            deal,
+            heap,
+            seen: TranspositionTable::new(),
+            best: MAX_MOVES,
+            best_moves: vec![],
+            verbose: true,
+        }
+    }
+
+    pub fn to_checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            game: self.game.clone(),
+            cap: self.cap,
+            suits: self.suits,
+            seed: self.seed,
+            deal: self.deal.clone(),
+            best: self.best,
+            best_moves: self.best_moves.clone(),
+            heap: self
+                .heap
+                .iter()
+                .map(|wdeck| HeapEntry {
+                    moves: wdeck.deck.win_moves(),
+                    depth: wdeck.depth,
+                    estimate: wdeck.moves,
+                    total: wdeck.total,
+                })
+                .collect(),
+            seen: self.seen.entries(),
+        }
+    }
+
+    pub fn from_checkpoint(checkpoint: &Checkpoint) -> Result<SlowSearch, String> {
+        let mut deal = checkpoint.deal.clone();
+        deal.reset_moves();
+        let mut heap = BinaryHeap::new();
+        for entry in &checkpoint.heap {
+            let deck = entry.deck(&deal)?;
+            heap.push(WeightedDeck {
+                hash: deck.hash(),
+                deck,
+                depth: entry.depth,
+                moves: entry.estimate,
+                total: entry.total,
+            });
+        }
+        let mut seen = TranspositionTable::new();
+        seen.restore(&checkpoint.seen)?;
+        Ok(SlowSearch {
+            game: checkpoint.game.clone(),
+            cap: checkpoint.cap,
+            suits: checkpoint.suits,
+            seed: checkpoint.seed,
+            deal,
+            heap,
+            seen,
+            best: checkpoint.best,
+            best_moves: checkpoint.best_moves.clone(),
+            verbose: true,
+        })
+    }
+
+    // expands the most promising position. 0 once there is nothing
+    // left to pick, else the best total of its children
+    pub fn pick<E: Evaluator + ?Sized>(
+        &mut self,
+        evaluator: &E,
+        seen_limit: Option<SeenLimit>,
+        observer: &mut Observer,
+    ) -> usize {
+        let verbose = self.verbose;
+        let best_before = self.best;
+        let heap = &mut self.heap;
+        let seen = &mut self.seen;
+        let wdeck = heap.pop();
+        if wdeck.is_none() {
+            return 0;
+        }
+        let wdeck = wdeck.unwrap();
+        let depth = wdeck.depth;
+        observer.report(Progress {
+            depth: depth as usize,
+            frontier: heap.len(),
+            best: Some(best_before).filter(|best| *best < MAX_MOVES),
+        });
+        if verbose {
+            print!("Picked {}+{}={} (", depth, wdeck.moves, wdeck.total);
+        }
+
+        let deck = wdeck.deck;
+        if deck.is_won() {
+            if verbose {
+                println!("WON");
+            }
+            return 0;
+        }
+        // no way to beat what we have
+        if depth as usize + deck.lower_bound() as usize >= best_before {
+            if verbose {
+                println!("pruned)");
+            }
+            return MAX_MOVES;
+        }
+        let mut moves = vec![];
+        deck.get_moves(&mut moves);
+        let mut best_total = MAX_MOVES;
+        let mut bestdeck: Option<Deck> = None;
+        let mut pruned = false;
+        let mut cancelled = false;
+        let mut children = vec![];
+        // seen and on the heap only once the pick is done, a cancelled
+        // pick is done again as a whole
+        let mut searched: Vec<(Deck, u64)> = vec![];
+        let mut estimated = vec![];
+
+        for m in &moves {
+            let mut newdeck = deck.apply_move(m);
+            let hash = newdeck.hash();
+            let mut child = ChildEstimate {
+                m: *m,
+                status: ChildStatus::Seen,
+                estimate: None,
+            };
+            let position = newdeck.position(hash);
+            if !seen.would_expand(&newdeck, hash, depth + 1)
+                || searched.iter().any(|(d, h)| d.position(*h) == position)
+            {
+                children.push(child);
+                continue;
+            }
+            if (depth + 1 + newdeck.lower_bound()) as usize >= best_before {
+                searched.push((newdeck, hash));
+                pruned = true;
+                child.status = ChildStatus::Pruned;
+                children.push(child);
+                continue;
+            }
+            child.status = ChildStatus::Searched;
+            let orig_move_index = newdeck.get_moves_index();
+            // only the cancel token, the progress is about the picks
+            let mut child_observer = Observer::new(observer.cancel.clone());
+            let won = newdeck.shortest_path_observed(
+                evaluator,
+                self.cap,
+                false,
+                None,
+                &mut TranspositionTable::with_limit(seen_limit),
+                &mut child_observer,
+            );
+            observer.stats.add_counts(&child_observer.stats);
+            if child_observer.stats.cancelled {
+                cancelled = true;
+                break;
+            }
+            searched.push((newdeck.clone(), hash));
+
+            match won {
+                Some(won) if won >= 0 => {
+                    let won = won as u32;
+                    child.estimate = Some(won);
+                    children.push(child);
+                    if verbose {
+                        print!("{} ", depth + won + 1);
+                    }
+                    if ((won + depth + 1) as usize) < best_total {
+                        best_total = (won + depth + 1) as usize;
+                        if best_total < best_before {
+                            bestdeck = Some(newdeck.clone());
+                        }
+                    }
+                    newdeck.set_moves_index(orig_move_index);
+                    estimated.push(WeightedDeck {
+                        deck: newdeck,
+                        hash,
+                        depth: depth + 1,
+                        moves: won,
+                        total: won + depth + 1,
+                    });
+                }
+                _ => children.push(child),
+            }
+        }
+        if verbose {
+            println!("{})", if cancelled { "cancelled" } else { "" });
+        }
+        if !cancelled {
+            for (newdeck, hash) in &searched {
+                seen.insert(newdeck, *hash, depth + 1);
+            }
+            heap.extend(estimated);
+        }
+        // if all were pruned, we just know they don't beat the best
+        let all_pruned = pruned && best_total == MAX_MOVES;
+        if best_total > wdeck.total as usize && !all_pruned && !cancelled && verbose {
+            println!("There was a slip!");
+            let report = SlipReport {
+                game: self.game.clone(),
+                cap: self.cap,
+                suits: self.suits,
+                seed: self.seed,
+                depth,
+                estimate: wdeck.moves,
+                best: (best_total - depth as usize) as u32,
+                position: deck.clone(),
+                children,
+            };
+            let filename = report.filename();
+            match report.save(&filename) {
+                Err(why) => panic!("{}", why),
+                Ok(_) => println!("successfully wrote to {}", filename),
+            }
+        }
+        if let Some(deck) = bestdeck {
+            let moves = deck.win_moves();
+            self.best = best_total;
+            self.best_moves = moves.clone();
+            if verbose {
+                let orig = &self.deal;
+                let mut deck = orig.clone();
+                let mut mc = 0;
+                for m in moves {
+                    if !m.is_off() {
+                        mc += 1;
+                    }
+                    print!("Move {}: {}", mc, deck.explain_move(&m));
+                    deck = deck.apply_move(&m);
+                    println!(" (Chaos {} Playable {})", deck.chaos(), deck.playable());
+                }
+                let lower_bound = orig.lower_bound() as usize;
+                println!(
+                    "Solution of {} moves, at least {} needed (gap {})",
+                    best_total,
+                    lower_bound,
+                    best_total - lower_bound
+                );
+            }
+        }
+        // the best child is kept, the position is picked again
+        if cancelled {
+            self.heap.push(WeightedDeck {
+                deck,
+                hash: wdeck.hash,
+                depth,
+                moves: wdeck.moves,
+                total: wdeck.total,
+            });
+            return MAX_MOVES;
+        }
+        best_total
+    }
+}
+
+#[cfg(test)]
+mod slowtests {
+    use super::*;
+    use crate::deck::ENDGAME;
+    use crate::evaluator::{DefaultEvaluator, Score};
+    use crate::progress::Cancel;
+    use std::cell::Cell;
+
+    #[test]
+    fn cancelled_child() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let mut search = SlowSearch::new("endgame.game", &deck, 3, 10);
+        search.verbose = false;
+        let cancel = Cancel::new();
+        cancel.cancel();
+        let mut observer = Observer::new(cancel);
+        // a cancelled child search is no proof there is no win
+        assert_eq!(
+            search.pick(&DefaultEvaluator, None, &mut observer),
+            MAX_MOVES
+        );
+        assert_eq!(search.heap.len(), 1);
+        assert_eq!(search.seen.len(), 0);
+        assert_eq!(search.best, MAX_MOVES);
+
+        let mut observer = Observer::default();
+        assert_eq!(search.pick(&DefaultEvaluator, None, &mut observer), 3);
+        assert_eq!(search.best_moves.len(), 3);
+    }
+
+    // cancels the searches once it scored that many positions
+    struct CancelAt {
+        cancel: Cancel,
+        calls: Cell<usize>,
+        at: usize,
+    }
+
+    impl Evaluator for CancelAt {
+        fn score(&self, deck: &Deck) -> Score {
+            self.calls.set(self.calls.get() + 1);
+            if self.calls.get() == self.at {
+                self.cancel.cancel();
+            }
+            DefaultEvaluator.score(deck)
+        }
+    }
+
+    #[test]
+    fn resume_cancelled() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let mut search = SlowSearch::new("endgame.game", &deck, 3, 10);
+        search.verbose = false;
+        let cancel = Cancel::new();
+        let evaluator = CancelAt {
+            cancel: cancel.clone(),
+            calls: Cell::new(0),
+            at: 2,
+        };
+        let mut observer = Observer::new(cancel);
+        // the first child won, the second was cancelled
+        assert_eq!(search.pick(&evaluator, None, &mut observer), MAX_MOVES);
+        assert_eq!(search.best, 3);
+        assert_eq!(search.best_moves.len(), 3);
+        // nothing else of the pick is kept
+        assert_eq!(search.seen.len(), 0);
+        assert_eq!(search.heap.len(), 1);
+
+        let mut resumed = SlowSearch::from_checkpoint(&search.to_checkpoint()).unwrap();
+        resumed.verbose = false;
+        let mut observer = Observer::default();
+        let mut picks = 0;
+        while resumed.pick(&DefaultEvaluator, None, &mut observer) != 0 {
+            picks += 1;
+            assert!(picks < 100);
+        }
+        assert_eq!(resumed.best, 3);
+        assert_eq!(resumed.best_moves, search.best_moves);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::deck::Deck;
+use crate::moves::Move;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Json,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "yaml" => Some(Format::Yaml),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct NumberedMove {
+    #[serde(flatten)]
+    pub m: Move,
+    // the count shown to players, it skips the off moves
+    #[serde(default)]
+    pub number: usize,
+}
+
+// what a search found and how it was run
+#[derive(Serialize, Deserialize)]
+pub struct Solution {
+    #[serde(default)]
+    pub game: String,
+    // of the deal after shuffling the unknown cards
+    #[serde(default)]
+    pub hash: String,
+    #[serde(default)]
+    pub suits: usize,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub cap: usize,
+    // the positions the search looked at
+    #[serde(default)]
+    pub nodes: usize,
+    #[serde(default)]
+    pub seconds: f64,
+    // the number of moves the solver reported, none for no win
+    #[serde(default)]
+    pub length: Option<usize>,
+    #[serde(default)]
+    pub lower_bound: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deal: Option<Deck>,
+    pub moves: Vec<NumberedMove>,
+}
+
+impl Solution {
+    pub fn new(game: &str, deal: &Deck) -> Solution {
+        Solution {
+            game: game.to_string(),
+            hash: format!("{:016x}", deal.hash()),
+            suits: 0,
+            seed: None,
+            cap: 0,
+            nodes: 0,
+            seconds: 0.,
+            length: None,
+            lower_bound: deal.lower_bound(),
+            deal: Some(deal.clone()),
+            moves: vec![],
+        }
+    }
+
+    pub fn set_moves(&mut self, moves: &[Move]) {
+        let mut mc = 0;
+        self.moves = moves
+            .iter()
+            .map(|m| {
+                if !m.is_off() {
+                    mc += 1;
+                }
+                NumberedMove { m: *m, number: mc }
+            })
+            .collect();
+    }
+
+    pub fn plain_moves(&self) -> Vec<Move> {
+        self.moves.iter().map(|nm| nm.m).collect()
+    }
+
+    pub fn to_text(&self, format: Format) -> String {
+        match format {
+            Format::Yaml => serde_yaml::to_string(self).expect("serializable"),
+            Format::Json => serde_json::to_string_pretty(self).expect("serializable") + "\n",
+        }
+    }
+
+    // reads both formats (json is yaml as well), including the
+    // archived files that have nothing but the moves
+    pub fn parse(contents: &str) -> Result<Solution, String> {
+        serde_yaml::from_str(contents).map_err(|e| format!("Invalid solution: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod solutiontests {
+    use super::*;
+    use crate::deck::ENDGAME;
+
+    #[test]
+    fn round_trip() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let mut solution = Solution::new("endgame.game", &deck);
+        solution.length = Some(3);
+        solution.seed = Some(7);
+        solution.set_moves(&[
+            Move::regular(1, 0, 0),
+            Move::regular(2, 0, 0),
+            Move::off(0, 0),
+        ]);
+        assert_eq!(solution.moves[2].number, 2);
+        for format in [Format::Yaml, Format::Json].iter() {
+            let text = solution.to_text(*format);
+            let parsed = Solution::parse(&text).expect("parsed");
+            assert_eq!(parsed.plain_moves(), solution.plain_moves());
+            assert_eq!(parsed.moves, solution.moves);
+            assert_eq!(parsed.hash, solution.hash);
+            assert_eq!(parsed.seed, Some(7));
+            assert_eq!(
+                parsed.deal.map(|deal| deal.to_string()),
+                Some(deck.to_string())
+            );
+        }
+        let yaml = solution.to_text(Format::Yaml);
+        // off is a boolean in yaml 1.1, so the key is quoted
+        assert!(yaml.contains("\"off\": true"));
+        assert!(!yaml.contains("talon"));
+        // a broken deal is an error, not a panic
+        assert!(Solution::parse("deal: \"Play0: XX\"\nmoves: []\n").is_err());
+    }
+}
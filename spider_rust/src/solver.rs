@@ -0,0 +1,394 @@
+use crate::checkpoint::Checkpoint;
+use crate::deck::Deck;
+use crate::evaluator::Evaluator;
+use crate::mcts::Mcts;
+use crate::moves::Move;
+use crate::nrpa::Nrpa;
+use crate::progress::Observer;
+use crate::recursion::Recursion;
+use crate::slow::SlowSearch;
+use crate::transposition::{SeenLimit, TranspositionTable};
+use std::time::{Duration, Instant};
+
+// what a solver may spend on one deal
+#[derive(Clone, Copy, Debug)]
+pub struct Budget {
+    // of every beam search
+    pub cap: usize,
+    pub seen_limit: Option<SeenLimit>,
+    // a single beam search isn't stopped by the clock
+    pub seconds: Option<f64>,
+    // of the monte carlo search, the playouts of nrpa. They stop at
+    // these or the seconds, whichever comes first, without both only
+    // when cancelled
+    pub iterations: Option<usize>,
+}
+
+impl Budget {
+    pub fn deadline(&self) -> Option<Instant> {
+        self.seconds
+            .map(|seconds| Instant::now() + Duration::from_secs_f64(seconds))
+    }
+}
+
+pub struct Outcome {
+    // the best win found
+    pub moves: Option<Vec<Move>>,
+    // the most promising line when cancelled before a win
+    pub line: Vec<Move>,
+    // by the seen limit
+    pub dropped: usize,
+    pub reexpanded: usize,
+}
+
+// a search from a deal to its moves. The counters, the progress and
+// the cancel token are in the observer
+pub trait Solver {
+    fn solve(&mut self, deck: &Deck, budget: &Budget, observer: &mut Observer) -> Outcome;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    Beam,
+    // A+ on top of the beam searches
+    Slow,
+    Recursion { slack: usize, trust: u32 },
+    Mcts,
+    Nrpa,
+}
+
+impl Strategy {
+    pub fn parse(name: &str, slack: usize, trust: u32) -> Option<Strategy> {
+        match name {
+            "beam" => Some(Strategy::Beam),
+            "slow" => Some(Strategy::Slow),
+            "recursion" => Some(Strategy::Recursion { slack, trust }),
+            "mcts" => Some(Strategy::Mcts),
+            "nrpa" => Some(Strategy::Nrpa),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Beam => "beam",
+            Strategy::Slow => "slow",
+            Strategy::Recursion { .. } => "recursion",
+            Strategy::Mcts => "mcts",
+            Strategy::Nrpa => "nrpa",
+        }
+    }
+
+    // the solvers that draw their moves at random
+    pub fn samples(&self) -> bool {
+        matches!(self, Strategy::Mcts | Strategy::Nrpa)
+    }
+
+    pub fn solver<'a>(
+        &self,
+        evaluator: &'a dyn Evaluator,
+        debug: bool,
+        seed: u64,
+    ) -> Box<dyn Solver + 'a> {
+        match *self {
+            Strategy::Beam => Box::new(Beam { evaluator, debug }),
+            Strategy::Slow => Box::new(Slow::new(evaluator, seed)),
+            Strategy::Recursion { slack, trust } => Box::new(RecursionSolver {
+                evaluator,
+                slack,
+                trust,
+            }),
+            Strategy::Mcts => Box::new(MctsSolver { seed }),
+            Strategy::Nrpa => Box::new(NrpaSolver { seed }),
+        }
+    }
+}
+
+pub struct Beam<'a> {
+    pub evaluator: &'a dyn Evaluator,
+    pub debug: bool,
+}
+
+impl Solver for Beam<'_> {
+    fn solve(&mut self, deck: &Deck, budget: &Budget, observer: &mut Observer) -> Outcome {
+        let mut deck = deck.clone();
+        let mut seen = TranspositionTable::with_limit(budget.seen_limit);
+        let won = deck.shortest_path_observed(
+            self.evaluator,
+            budget.cap,
+            self.debug,
+            None,
+            &mut seen,
+            observer,
+        );
+        let cancelled = observer.stats.cancelled;
+        let moves = won
+            .filter(|moves| *moves > 0 && !cancelled)
+            .map(|_| deck.win_moves());
+        Outcome {
+            moves,
+            line: if cancelled { deck.win_moves() } else { vec![] },
+            dropped: seen.evictions(),
+            reexpanded: seen.reexpansions(),
+        }
+    }
+}
+
+// A+ on top of the beam searches, from the deal or a checkpoint
+pub struct Slow<'a> {
+    pub evaluator: &'a dyn Evaluator,
+    pub seed: u64,
+    // of the checkpoints and slip reports
+    pub game: String,
+    pub suits: usize,
+    // print the picks and write slip reports
+    pub verbose: bool,
+    // saved now and then and when stopped before it was done
+    pub checkpoint: Option<String>,
+    pub checkpoint_every: Duration,
+    pub resume: Option<Checkpoint>,
+}
+
+impl<'a> Slow<'a> {
+    pub fn new(evaluator: &'a dyn Evaluator, seed: u64) -> Slow<'a> {
+        Slow {
+            evaluator,
+            seed,
+            game: String::new(),
+            suits: 0,
+            verbose: false,
+            checkpoint: None,
+            checkpoint_every: Duration::from_secs(300),
+            resume: None,
+        }
+    }
+
+    fn save(&self, search: &SlowSearch) {
+        if let Some(path) = &self.checkpoint {
+            search
+                .to_checkpoint()
+                .save(path)
+                .unwrap_or_else(|e| panic!("{}", e));
+            if self.verbose {
+                println!("Wrote checkpoint to {}", path);
+            }
+        }
+    }
+}
+
+impl Solver for Slow<'_> {
+    fn solve(&mut self, deck: &Deck, budget: &Budget, observer: &mut Observer) -> Outcome {
+        let start = Instant::now();
+        let deadline = budget.deadline();
+        // none when resumed
+        let mut first_moves = vec![];
+        let mut search = match self.resume.take() {
+            Some(checkpoint) => {
+                if checkpoint.deal.hash() != deck.hash() {
+                    panic!("The checkpoint of {} is of another deal", checkpoint.game);
+                }
+                // the children are searched with the cap of the checkpoint
+                let search =
+                    SlowSearch::from_checkpoint(&checkpoint).unwrap_or_else(|e| panic!("{}", e));
+                if self.verbose {
+                    println!(
+                        "Resumed with {} positions to pick and {} seen, best {}",
+                        search.heap.len(),
+                        search.seen.len(),
+                        search.best
+                    );
+                }
+                search
+            }
+            None => {
+                let mut beam = Beam {
+                    evaluator: self.evaluator,
+                    debug: false,
+                };
+                let first = beam.solve(deck, budget, observer);
+                first_moves = match first.moves {
+                    Some(moves) => moves,
+                    None => return first,
+                };
+                let mut search =
+                    SlowSearch::new(&self.game, deck, first_moves.len() as u32, budget.cap);
+                search.suits = self.suits;
+                search.seed = Some(self.seed);
+                search
+            }
+        };
+        search.verbose = self.verbose;
+        let mut saved = Instant::now();
+        let mut done = false;
+        while !observer.is_cancelled() && deadline.is_none_or(|d| Instant::now() < d) {
+            if search.pick(self.evaluator, budget.seen_limit, observer) == 0 {
+                if self.verbose {
+                    println!(
+                        "Seen {} positions, {} found again",
+                        search.seen.len(),
+                        search.seen.hits()
+                    );
+                }
+                done = true;
+                break;
+            }
+            if saved.elapsed() >= self.checkpoint_every {
+                self.save(&search);
+                saved = Instant::now();
+            }
+        }
+        if !done {
+            self.save(&search);
+        }
+        observer.stats.seconds = start.elapsed().as_secs_f64();
+        observer.stats.cancelled = observer.is_cancelled();
+        // stopped before the picks beat the first beam search
+        let improved = !search.best_moves.is_empty()
+            && (first_moves.is_empty() || search.best < first_moves.len());
+        let moves = if improved {
+            search.best_moves
+        } else {
+            first_moves
+        };
+        Outcome {
+            moves: Some(moves).filter(|moves| !moves.is_empty()),
+            line: vec![],
+            dropped: search.seen.evictions(),
+            reexpanded: search.seen.reexpansions(),
+        }
+    }
+}
+
+pub struct RecursionSolver<'a> {
+    pub evaluator: &'a dyn Evaluator,
+    pub slack: usize,
+    pub trust: u32,
+}
+
+impl Solver for RecursionSolver<'_> {
+    fn solve(&mut self, deck: &Deck, budget: &Budget, observer: &mut Observer) -> Outcome {
+        let start = Instant::now();
+        let mut recursion = Recursion::new(
+            self.evaluator,
+            budget.cap,
+            self.slack,
+            self.trust,
+            budget.seen_limit,
+        );
+        recursion.deadline = budget.deadline();
+        let moves = recursion.search(deck, observer);
+        observer.stats.seconds = start.elapsed().as_secs_f64();
+        observer.stats.cancelled = observer.is_cancelled();
+        Outcome {
+            moves,
+            line: vec![],
+            dropped: 0,
+            reexpanded: 0,
+        }
+    }
+}
+
+pub struct MctsSolver {
+    pub seed: u64,
+}
+
+impl Solver for MctsSolver {
+    fn solve(&mut self, deck: &Deck, budget: &Budget, observer: &mut Observer) -> Outcome {
+        let start = Instant::now();
+        let mut mcts = Mcts::new(self.seed);
+        let moves = mcts.search(deck, budget.iterations, budget.deadline(), observer);
+        observer.stats.expanded = mcts.nodes();
+        observer.stats.generated = mcts.rollout_moves;
+        observer.stats.seconds = start.elapsed().as_secs_f64();
+        observer.stats.cancelled = observer.is_cancelled();
+        Outcome {
+            line: if moves.is_none() && observer.stats.cancelled {
+                mcts.principal_line()
+            } else {
+                vec![]
+            },
+            moves,
+            dropped: 0,
+            reexpanded: 0,
+        }
+    }
+}
+
+pub struct NrpaSolver {
+    pub seed: u64,
+}
+
+impl Solver for NrpaSolver {
+    fn solve(&mut self, deck: &Deck, budget: &Budget, observer: &mut Observer) -> Outcome {
+        let start = Instant::now();
+        let mut nrpa = Nrpa::new(deck, self.seed, budget.iterations, budget.deadline());
+        let moves = nrpa.search(observer);
+        observer.stats.expanded = nrpa.playouts;
+        observer.stats.generated = nrpa.moves;
+        observer.stats.seconds = start.elapsed().as_secs_f64();
+        observer.stats.cancelled = observer.is_cancelled();
+        Outcome {
+            moves,
+            line: vec![],
+            dropped: 0,
+            reexpanded: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod solvertests {
+    use super::*;
+    use crate::deck::ENDGAME;
+    use crate::evaluator::DefaultEvaluator;
+
+    #[test]
+    fn strategies() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        assert_eq!(Strategy::parse("beam", 5, 20), Some(Strategy::Beam));
+        assert_eq!(Strategy::parse("dfs", 5, 20), None);
+        let budget = Budget {
+            cap: 10,
+            seen_limit: None,
+            seconds: None,
+            iterations: Some(200),
+        };
+        for name in &["beam", "slow", "recursion", "mcts", "nrpa"] {
+            let strategy = Strategy::parse(name, 5, 20).unwrap();
+            assert_eq!(strategy.name(), *name);
+            let mut solver = strategy.solver(&DefaultEvaluator, false, 1);
+            let mut observer = Observer::default();
+            let outcome = solver.solve(&deck, &budget, &mut observer);
+            let moves = outcome.moves.expect("won");
+            assert_eq!(moves.len(), 3, "{}", name);
+            let mut won = deck.clone();
+            for m in moves {
+                won = won.try_apply_move(&m).expect("legal");
+            }
+            assert!(won.is_won(), "{}", name);
+        }
+
+        // out of time right after the first beam search, saved and
+        // picked up again where it stopped
+        let path = std::env::temp_dir()
+            .join(format!("spider-slow-{}", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let mut slow = Slow::new(&DefaultEvaluator, 7);
+        slow.checkpoint = Some(path.clone());
+        let out_of_time = Budget {
+            seconds: Some(0.),
+            ..budget
+        };
+        let outcome = slow.solve(&deck, &out_of_time, &mut Observer::default());
+        assert_eq!(outcome.moves.map(|moves| moves.len()), Some(3));
+        let checkpoint = Checkpoint::load(&path).expect("saved");
+        assert_eq!(checkpoint.seed, Some(7));
+        assert_eq!(checkpoint.heap.len(), 1);
+        let mut slow = Slow::new(&DefaultEvaluator, 7);
+        slow.resume = Some(checkpoint);
+        let outcome = slow.solve(&deck, &budget, &mut Observer::default());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(outcome.moves.map(|moves| moves.len()), Some(3));
+    }
+}
@@ -0,0 +1,133 @@
+use serde::Serialize;
+use std::fs;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsOutput {
+    Report,
+    Json,
+}
+
+impl StatsOutput {
+    pub fn parse(name: &str) -> Option<StatsOutput> {
+        match name {
+            "report" => Some(StatsOutput::Report),
+            "json" => Some(StatsOutput::Json),
+            _ => None,
+        }
+    }
+}
+
+// one level of the beam search
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct DepthStats {
+    pub depth: usize,
+    // the new positions before the cap was applied
+    pub candidates: usize,
+    // the positions kept for the next depth, by free talons
+    pub frontier: [usize; 6],
+    pub seconds: f64,
+}
+
+// what a beam search did, filled in by Deck::shortest_path_with_stats
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SearchStats {
+    // positions reached by applying a move
+    pub generated: usize,
+    // positions the moves were generated for
+    pub expanded: usize,
+    // generated positions that were already seen
+    pub duplicates: usize,
+    pub interned_piles: usize,
+    // of the whole process, as far as the system tells
+    pub peak_memory_kb: Option<u64>,
+    pub seconds: f64,
+    // stopped before it was done
+    pub cancelled: bool,
+    pub depths: Vec<DepthStats>,
+}
+
+impl SearchStats {
+    // the counters of a search run on behalf of this one
+    pub fn add_counts(&mut self, other: &SearchStats) {
+        self.generated += other.generated;
+        self.expanded += other.expanded;
+        self.duplicates += other.duplicates;
+    }
+
+    pub fn report(&self) -> String {
+        let mut result = format!(
+            "Generated {} positions, expanded {}, {} duplicates\n",
+            self.generated, self.expanded, self.duplicates
+        );
+        result += &format!("Interned {} piles", self.interned_piles);
+        if let Some(kb) = self.peak_memory_kb {
+            result += &format!(", peak memory {} MB", kb / 1024);
+        }
+        result += &format!(" in {:.2} seconds\n", self.seconds);
+        if self.cancelled {
+            result += "Cancelled\n";
+        }
+        result += &format!(
+            "{:>5} {:>10} {:>6} {:>6} {:>6} {:>6} {:>6} {:>6} {:>8}\n",
+            "depth", "candidates", "t0", "t1", "t2", "t3", "t4", "t5", "seconds"
+        );
+        for d in &self.depths {
+            result += &format!("{:>5} {:>10}", d.depth, d.candidates);
+            for count in d.frontier.iter() {
+                result += &format!(" {:>6}", count);
+            }
+            result += &format!(" {:>8.3}\n", d.seconds);
+        }
+        result
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serializable") + "\n"
+    }
+
+    pub fn to_text(&self, output: StatsOutput) -> String {
+        match output {
+            StatsOutput::Report => self.report(),
+            StatsOutput::Json => self.to_json(),
+        }
+    }
+}
+
+// the high water mark of the resident memory, only known on linux
+pub fn peak_memory_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod statstests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let stats = SearchStats {
+            generated: 10,
+            expanded: 3,
+            duplicates: 2,
+            interned_piles: 40,
+            peak_memory_kb: Some(2048),
+            seconds: 0.5,
+            cancelled: false,
+            depths: vec![DepthStats {
+                depth: 0,
+                candidates: 8,
+                frontier: [0, 3, 0, 0, 0, 0],
+                seconds: 0.25,
+            }],
+        };
+        let report = stats.report();
+        assert!(report.starts_with("Generated 10 positions, expanded 3, 2 duplicates\n"));
+        assert!(report.contains("peak memory 2 MB"));
+        let numbers: Vec<&str> = report.lines().nth(3).unwrap().split_whitespace().collect();
+        assert_eq!(numbers, ["0", "8", "0", "3", "0", "0", "0", "0", "0.250"]);
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(json["depths"][0]["frontier"][1], 3);
+        assert_eq!(json["duplicates"], 2);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::deck::Deck;
+use crate::deck::Position;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::{BuildHasherDefault, Hasher};
+
+// positions carry their seahash already, no need to hash it again
+#[derive(Default)]
+pub struct PassThroughHasher(u64);
+
+impl Hasher for PassThroughHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {
+        panic!("only u64 keys are supported");
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = value;
+    }
+}
+
+pub type PassThrough = BuildHasherDefault<PassThroughHasher>;
+
+// how big the table may grow before old positions are dropped
+#[derive(Clone, Copy, Debug)]
+pub struct SeenLimit {
+    pub max_entries: usize,
+    // positions reached in the last keep_depths depths survive
+    pub keep_depths: u32,
+}
+
+// a position of the table written out, for checkpoints
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SeenEntry {
+    pub depth: u32,
+    pub piles: Vec<String>,
+}
+
+// remembers every position a search visited and the shallowest
+// depth it was reached at. Hits are verified against the full
+// position, so a hash collision no longer prunes a real position
+pub struct TranspositionTable {
+    depths: HashMap<Position, u32, PassThrough>,
+    hits: usize,
+    limit: Option<SeenLimit>,
+    // fingerprints of dropped positions, 8 bytes instead of a full
+    // position - only used to count how often we expand them again.
+    // At most max_entries of them, the older ones are forgotten
+    evicted: HashSet<u64, PassThrough>,
+    evictions: usize,
+    reexpansions: usize,
+}
+
+impl TranspositionTable {
+    pub fn new() -> TranspositionTable {
+        TranspositionTable {
+            depths: HashMap::default(),
+            hits: 0,
+            limit: None,
+            evicted: HashSet::default(),
+            evictions: 0,
+            reexpansions: 0,
+        }
+    }
+
+    pub fn with_limit(limit: Option<SeenLimit>) -> TranspositionTable {
+        let mut table = TranspositionTable::new();
+        table.limit = limit;
+        table
+    }
+
+    // returns true if the position needs to be expanded, i.e. it
+    // was never seen or only at a deeper level than now
+    pub fn insert(&mut self, deck: &Deck, hash: u64, depth: u32) -> bool {
+        let position = deck.position(hash);
+        match self.depths.get_mut(&position) {
+            Some(seen_depth) => {
+                self.hits += 1;
+                if *seen_depth <= depth {
+                    return false;
+                }
+                *seen_depth = depth;
+                true
+            }
+            None => {
+                if self.evicted.remove(&hash) {
+                    self.reexpansions += 1;
+                }
+                self.depths.insert(position, depth);
+                true
+            }
+        }
+    }
+
+    // whether insert would ask to expand the position, without
+    // remembering it
+    pub fn would_expand(&self, deck: &Deck, hash: u64, depth: u32) -> bool {
+        match self.depths.get(&deck.position(hash)) {
+            Some(seen_depth) => *seen_depth > depth,
+            None => true,
+        }
+    }
+
+    pub fn entries(&self) -> Vec<SeenEntry> {
+        self.depths
+            .iter()
+            .map(|(position, depth)| SeenEntry {
+                depth: *depth,
+                piles: position.piles(),
+            })
+            .collect()
+    }
+
+    pub fn restore(&mut self, entries: &[SeenEntry]) -> Result<(), String> {
+        for entry in entries {
+            let position = Position::parse(&entry.piles)?;
+            self.depths.insert(position, entry.depth);
+        }
+        Ok(())
+    }
+
+    // to be called once the search reached a new depth. If the table
+    // outgrew its limit, the positions reached before the last
+    // keep_depths depths are forgotten - and if that is not enough,
+    // everything but the current depth
+    pub fn age(&mut self, depth: u32) {
+        let limit = match self.limit {
+            None => return,
+            Some(limit) => limit,
+        };
+        if self.depths.len() <= limit.max_entries {
+            return;
+        }
+        let mut cutoff = depth.saturating_sub(limit.keep_depths);
+        loop {
+            if self.evicted.len() >= limit.max_entries {
+                self.evicted.clear();
+            }
+            let evicted = &mut self.evicted;
+            let before = self.depths.len();
+            self.depths.retain(|position, seen_depth| {
+                if *seen_depth < cutoff {
+                    if evicted.len() < limit.max_entries {
+                        evicted.insert(position.hash());
+                    }
+                    return false;
+                }
+                true
+            });
+            self.evictions += before - self.depths.len();
+            if self.depths.len() <= limit.max_entries || cutoff >= depth {
+                break;
+            }
+            cutoff = depth;
+        }
+    }
+
+    #[cfg(test)]
+    pub fn contains(&self, deck: &Deck, hash: u64) -> bool {
+        self.depths.contains_key(&deck.position(hash))
+    }
+
+    pub fn len(&self) -> usize {
+        self.depths.len()
+    }
+
+    // how often a position was found again
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn evictions(&self) -> usize {
+        self.evictions
+    }
+
+    // how often a dropped position had to be expanded again
+    pub fn reexpansions(&self) -> usize {
+        self.reexpansions
+    }
+}
+
+#[cfg(test)]
+mod transpositiontests {
+    use super::*;
+    use crate::deck::ENDGAME;
+    use crate::moves::Move;
+
+    #[test]
+    fn shallower_depth_reexpands() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let mut table = TranspositionTable::new();
+        assert!(table.insert(&deck, deck.hash(), 5));
+        assert!(!table.insert(&deck, deck.hash(), 5));
+        assert!(!table.insert(&deck, deck.hash(), 7));
+        assert!(table.insert(&deck, deck.hash(), 3));
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.hits(), 3);
+    }
+
+    #[test]
+    fn collisions_are_verified() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let other = deck.apply_move(&Move::regular(1, 0, 0));
+        let mut table = TranspositionTable::new();
+        // pretend both positions hash the same
+        assert!(table.insert(&deck, 42, 1));
+        assert!(table.insert(&other, 42, 1));
+        assert!(table.contains(&other, 42));
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn entries_restore() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let other = deck.apply_move(&Move::regular(1, 0, 0));
+        let mut table = TranspositionTable::new();
+        table.insert(&deck, deck.hash(), 0);
+        table.insert(&other, other.hash(), 1);
+        assert!(!table.would_expand(&other, other.hash(), 1));
+        assert!(table.would_expand(&other, other.hash(), 0));
+        let entries = table.entries();
+        assert_eq!(entries.len(), 2);
+        let mut restored = TranspositionTable::new();
+        restored.restore(&entries).expect("restored");
+        assert!(restored.contains(&deck, deck.hash()));
+        assert!(!restored.insert(&other, other.hash(), 2));
+        assert!(restored
+            .restore(&[SeenEntry {
+                depth: 0,
+                piles: vec!["KS".to_string()]
+            }])
+            .is_err());
+    }
+
+    #[test]
+    fn limit_drops_old_depths() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let other = deck.apply_move(&Move::regular(1, 0, 0));
+        let third = other.apply_move(&Move::regular(2, 0, 0));
+        let mut table = TranspositionTable::with_limit(Some(SeenLimit {
+            max_entries: 2,
+            keep_depths: 1,
+        }));
+        table.insert(&deck, deck.hash(), 0);
+        table.insert(&other, other.hash(), 1);
+        table.age(2);
+        // still within the limit
+        assert_eq!(table.len(), 2);
+        table.insert(&third, third.hash(), 2);
+        table.age(2);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.evictions(), 1);
+        assert!(!table.contains(&deck, deck.hash()));
+        // the dropped position is expanded again and counted
+        assert!(table.insert(&deck, deck.hash(), 3));
+        assert_eq!(table.reexpansions(), 1);
+    }
+
+    #[test]
+    fn limit_caps_fingerprints() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let other = deck.apply_move(&Move::regular(1, 0, 0));
+        let third = other.apply_move(&Move::regular(2, 0, 0));
+        let mut table = TranspositionTable::with_limit(Some(SeenLimit {
+            max_entries: 1,
+            keep_depths: 0,
+        }));
+        table.insert(&deck, deck.hash(), 0);
+        table.insert(&other, other.hash(), 1);
+        table.age(1);
+        assert_eq!(table.evicted.len(), 1);
+        table.insert(&third, third.hash(), 2);
+        table.age(2);
+        // only the latest dropped position is remembered
+        assert_eq!(table.evictions(), 2);
+        assert_eq!(table.evicted.len(), 1);
+        assert!(table.insert(&other, other.hash(), 3));
+        assert_eq!(table.reexpansions(), 1);
+        assert!(table.insert(&deck, deck.hash(), 3));
+        assert_eq!(table.reexpansions(), 1);
+    }
+}
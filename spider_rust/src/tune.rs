@@ -0,0 +1,199 @@
+use crate::deck::Deck;
+use crate::evaluator::{WeightedEvaluator, Weights, WEIGHT_NAMES};
+use crate::transposition::TranspositionTable;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+pub struct TuneGame {
+    pub filename: String,
+    pub suits: usize,
+}
+
+// the number of suits of a directory named like "4suits", else suits
+pub fn suits_of(dir: &Path, suits: usize) -> usize {
+    dir.file_name()
+        .and_then(|name| name.to_string_lossy().strip_suffix("suits")?.parse().ok())
+        .unwrap_or(suits)
+}
+
+// the games of a directory - the ones in directories named like
+// "4suits" are played with that number of suits, other
+// subdirectories are left out
+pub fn collect_games(dir: &Path, suits: usize) -> Vec<TuneGame> {
+    let suits = suits_of(dir, suits);
+    let mut games = vec![];
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|why| panic!("couldn't read {}: {}", dir.display(), why))
+        .map(|entry| entry.expect("directory entry").path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            if suits_of(&path, 0) > 0 {
+                games.extend(collect_games(&path, suits));
+            }
+        } else if path.extension().is_some_and(|ext| ext == "game") {
+            games.push(TuneGame {
+                filename: path.to_string_lossy().to_string(),
+                suits,
+            });
+        }
+    }
+    games
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub wins: usize,
+    // summed up over the won games
+    pub moves: usize,
+}
+
+impl Outcome {
+    // more wins first, then the shorter average solution
+    pub fn better_than(&self, other: &Outcome) -> bool {
+        if self.wins != other.wins {
+            return self.wins > other.wins;
+        }
+        self.moves * other.wins < other.moves * self.wins
+    }
+
+    pub fn average(&self) -> f64 {
+        if self.wins == 0 {
+            return 0.;
+        }
+        self.moves as f64 / self.wins as f64
+    }
+}
+
+pub fn evaluate(games: &[TuneGame], weights: &Weights, cap: usize, seed: u64) -> Outcome {
+    let evaluator = WeightedEvaluator::new(*weights);
+    let mut outcome = Outcome { wins: 0, moves: 0 };
+    for game in games {
+        let contents =
+            fs::read_to_string(&game.filename).expect("Something went wrong reading the file");
+        let mut deck = Deck::parse(&contents);
+        deck.shuffle_unknowns_with_seed(game.suits, seed);
+        let mut seen = TranspositionTable::new();
+        if let Some(moves) = deck.shortest_path_with(&evaluator, cap, false, None, &mut seen) {
+            if moves > 0 {
+                outcome.wins += 1;
+                outcome.moves += moves as usize;
+            }
+        }
+    }
+    outcome
+}
+
+fn write_weights(filename: &str, weights: &Weights, outcome: &Outcome) {
+    let mut file = match File::create(filename) {
+        Err(why) => panic!("couldn't create {}: {}", filename, why),
+        Ok(file) => file,
+    };
+    let text = format!(
+        "# {} wins, {:.2} moves on average\n{}",
+        outcome.wins,
+        outcome.average(),
+        weights
+    );
+    if let Err(why) = file.write_all(text.as_bytes()) {
+        panic!("couldn't write to {} {}", filename, why);
+    }
+}
+
+// coordinate descent: move one weight at a time by its step, keep
+// what improves and halve the steps once nothing does anymore
+pub fn tune(games: &[TuneGame], start: Weights, cap: usize, seed: u64, output: &str) -> Weights {
+    let mut best = start;
+    let mut best_outcome = evaluate(games, &best, cap, seed);
+    println!(
+        "Start: {} of {} won, {:.2} moves",
+        best_outcome.wins,
+        games.len(),
+        best_outcome.average()
+    );
+    write_weights(output, &best, &best_outcome);
+
+    let mut steps: Vec<i64> = WEIGHT_NAMES
+        .iter()
+        .map(|name| (best.get(name).unwrap().abs() / 4).max(1))
+        .collect();
+    while steps.iter().any(|step| *step > 0) {
+        let mut improved = false;
+        for (i, name) in WEIGHT_NAMES.iter().enumerate() {
+            if steps[i] == 0 {
+                continue;
+            }
+            for direction in [1, -1].iter() {
+                let mut candidate = best;
+                candidate.set(name, best.get(name).unwrap() + direction * steps[i]);
+                let outcome = evaluate(games, &candidate, cap, seed);
+                if outcome.better_than(&best_outcome) {
+                    println!(
+                        "{} {}: {} won, {:.2} moves",
+                        name,
+                        candidate.get(name).unwrap(),
+                        outcome.wins,
+                        outcome.average()
+                    );
+                    best = candidate;
+                    best_outcome = outcome;
+                    improved = true;
+                    write_weights(output, &best, &best_outcome);
+                    break;
+                }
+            }
+        }
+        if !improved {
+            for step in steps.iter_mut() {
+                *step /= 2;
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tunetests {
+    use super::*;
+
+    #[test]
+    fn outcome_order() {
+        let two = Outcome {
+            wins: 2,
+            moves: 300,
+        };
+        let three = Outcome {
+            wins: 3,
+            moves: 600,
+        };
+        let short = Outcome {
+            wins: 2,
+            moves: 280,
+        };
+        assert!(three.better_than(&two));
+        assert!(short.better_than(&two));
+        assert!(!two.better_than(&two));
+        assert_eq!(short.average(), 140.);
+    }
+
+    #[test]
+    fn suits_from_directories() {
+        let games = collect_games(Path::new("../examples"), 2);
+        assert!(games
+            .iter()
+            .any(|g| g.filename.ends_with("1suits/example59.game") && g.suits == 1));
+        assert!(games
+            .iter()
+            .any(|g| g.filename.ends_with("4suits/example16.game") && g.suits == 4));
+        assert!(games
+            .iter()
+            .any(|g| g.filename.ends_with("/example7.game") && g.suits == 2));
+        // also when the directory is given itself
+        let games = collect_games(Path::new("../examples/1suits"), 2);
+        assert!(!games.is_empty());
+        assert!(games.iter().all(|g| g.suits == 1));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::deck::{Deck, IllegalMove};
+use crate::moves::Move;
+use crate::solution::Solution;
+
+// the moves of a yaml or json solution and the deal they were found
+// for, if the solution has it - with the unknown cards as they were
+// shuffled then
+pub fn parse_solution(contents: &str) -> Result<(Vec<Move>, Option<Deck>), String> {
+    let solution = Solution::parse(contents)?;
+    Ok((solution.plain_moves(), solution.deal))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Replay {
+    Won(usize),
+    // all moves were legal, but the game isn't over
+    NotWon(usize),
+    // counting from 1
+    Illegal(usize, Move, IllegalMove),
+}
+
+pub fn replay(deck: &Deck, moves: &[Move]) -> Replay {
+    let mut deck = deck.clone();
+    deck.reset_moves();
+    for (i, m) in moves.iter().enumerate() {
+        deck = match deck.try_apply_move(m) {
+            Ok(deck) => deck,
+            Err(why) => return Replay::Illegal(i + 1, *m, why),
+        };
+        deck.reset_moves();
+    }
+    if deck.is_won() {
+        Replay::Won(moves.len())
+    } else {
+        Replay::NotWon(moves.len())
+    }
+}
+
+// a yaml or json solution or moves in notation, the latter never
+// come with their deal
+pub fn load_moves(contents: &str, deck: &Deck) -> Result<(Vec<Move>, Option<Deck>), String> {
+    if contents.trim_start().starts_with('{')
+        || contents.lines().any(|line| line.trim_end() == "moves:")
+    {
+        parse_solution(contents)
+    } else {
+        Ok((Move::parse_list(contents, deck)?, None))
+    }
+}
+
+// the deck after move number, counted like the solutions print them:
+// off moves don't count and are played along with the move before
+pub fn seek(deck: &Deck, moves: &[Move], number: usize) -> Result<Deck, String> {
+    let mut deck = deck.clone();
+    deck.reset_moves();
+    let mut mc = 0;
+    for (i, m) in moves.iter().enumerate() {
+        if !m.is_off() {
+            if mc == number {
+                return Ok(deck);
+            }
+            mc += 1;
+        }
+        deck = deck
+            .try_apply_move(m)
+            .map_err(|why| format!("Move {} ({}) is illegal: {}", i + 1, m.notation(), why))?;
+        deck.reset_moves();
+    }
+    if mc < number {
+        return Err(format!("The solution has only {} moves", mc));
+    }
+    Ok(deck)
+}
+
+#[cfg(test)]
+mod verifytests {
+    use super::*;
+    use crate::deck::ENDGAME;
+    use crate::solution::Format;
+
+    #[test]
+    fn parse() {
+        let text = "lower_bound: 1
+moves:
+  - from: 1
+    to: 0
+    index: 0
+    number: 1
+  - from: 2
+    to: 0
+    index: 0
+    number: 2
+  - from: 0
+    to: 0
+    index: 0
+    off: true
+    number: 2
+";
+        let (moves, deal) = parse_solution(text).expect("parsed");
+        assert!(deal.is_none());
+        assert_eq!(
+            moves,
+            vec![
+                Move::regular(1, 0, 0),
+                Move::regular(2, 0, 0),
+                Move::off(0, 0)
+            ]
+        );
+        let deck = Deck::parse(&ENDGAME.to_string());
+        assert_eq!(replay(&deck, &moves), Replay::Won(3));
+        assert_eq!(replay(&deck, &moves[..2]), Replay::NotWon(2));
+        assert_eq!(
+            replay(&deck, &moves[1..]),
+            Replay::Illegal(1, Move::regular(2, 0, 0), IllegalMove::DoesNotFit)
+        );
+        assert!(parse_solution("moves:\n  - from: one\n").is_err());
+        assert!(parse_solution("moves:\n  - form: 1\n").is_err());
+    }
+
+    #[test]
+    fn seek_moves() {
+        let deck = Deck::parse(&ENDGAME.to_string());
+        let (moves, _) = load_moves("2>1@0\n3>1@0\noff 1\n", &deck).expect("parsed");
+        assert_eq!(moves.len(), 3);
+        assert_eq!(
+            seek(&deck, &moves, 0).unwrap().to_string(),
+            deck.to_string()
+        );
+        let after_one = seek(&deck, &moves, 1).unwrap();
+        assert_eq!(after_one.pile_count(0), 5);
+        // the off move comes along with the second
+        assert!(seek(&deck, &moves, 2).unwrap().is_won());
+        assert!(seek(&deck, &moves, 3).is_err());
+        assert!(seek(&deck, &moves[1..], 1).is_err());
+    }
+
+    #[test]
+    fn archived() {
+        let contents = std::fs::read_to_string("../examples/example36.game").expect("game");
+        let deck = Deck::parse(&contents);
+        let contents =
+            std::fs::read_to_string("results/example36.game.5000.yaml").expect("solution");
+        let (moves, _) = parse_solution(&contents).expect("parsed");
+        assert_eq!(replay(&deck, &moves), Replay::Won(moves.len()));
+    }
+
+    #[test]
+    fn with_deal() {
+        let deal = Deck::parse(&ENDGAME.to_string());
+        let mut solution = Solution::new("endgame.game", &deal);
+        solution.set_moves(&[
+            Move::regular(1, 0, 0),
+            Move::regular(2, 0, 0),
+            Move::off(0, 0),
+        ]);
+        let (moves, written) = parse_solution(&solution.to_text(Format::Yaml)).expect("parsed");
+        let written = written.expect("deal");
+        assert_eq!(written.to_string(), deal.to_string());
+        assert_eq!(replay(&written, &moves), Replay::Won(3));
+    }
+}